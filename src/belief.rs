@@ -0,0 +1,134 @@
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use crate::save::{Save, SaveError, SaveReader, SaveWriter};
+
+/// Things a human can hold an opinion about. Weights run from -1 (certain it's false) to 1
+/// (certain it's true), with 0 meaning undecided.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, PartialOrd, Ord)]
+pub enum Belief {
+    GodExists,
+    GodIsBenevolent,
+    GodControlsWeather,
+    GodGrantsHarvest,
+}
+
+pub const ALL_BELIEFS: [Belief; 4] = [
+    Belief::GodExists,
+    Belief::GodIsBenevolent,
+    Belief::GodControlsWeather,
+    Belief::GodGrantsHarvest,
+];
+
+pub struct Beliefs {
+    weights: BTreeMap<Belief, f32>,
+}
+
+impl Belief {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Belief::GodExists => "GodExists",
+            Belief::GodIsBenevolent => "GodIsBenevolent",
+            Belief::GodControlsWeather => "GodControlsWeather",
+            Belief::GodGrantsHarvest => "GodGrantsHarvest",
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            Belief::GodExists => "God exists",
+            Belief::GodIsBenevolent => "God is kind",
+            Belief::GodControlsWeather => "God sends weather",
+            Belief::GodGrantsHarvest => "God grants harvest",
+        }
+    }
+}
+
+impl FromStr for Belief {
+    type Err = ();
+
+    fn from_str(name: &str) -> Result<Belief, ()> {
+        ALL_BELIEFS
+            .iter()
+            .find(|belief| belief.name() == name)
+            .cloned()
+            .ok_or(())
+    }
+}
+
+impl Beliefs {
+    pub fn new() -> Beliefs {
+        let mut weights = BTreeMap::new();
+        for &belief in ALL_BELIEFS.iter() {
+            weights.insert(belief, 0.0);
+        }
+        // everyone starts out vaguely aware that something might be up there
+        weights.insert(Belief::GodExists, 0.2);
+        Beliefs { weights: weights }
+    }
+
+    pub fn weight(&self, belief: Belief) -> f32 {
+        *self.weights.get(&belief).unwrap_or(&0.0)
+    }
+
+    /// Moves a belief towards `evidence` (-1 to 1). `strength` is how convincing the experience
+    /// was, from 0 (not at all) to 1 (adopt the evidence outright).
+    pub fn reinforce(&mut self, belief: Belief, evidence: f32, strength: f32) {
+        let weight = self.weights.entry(belief).or_insert(0.0);
+        *weight = (*weight + (evidence - *weight) * strength)
+            .max(-1.0)
+            .min(1.0);
+    }
+
+    /// How worthwhile prayer seems, from 0 to 1: the god has to exist and have some say over
+    /// the things that matter day to day.
+    pub fn piety(&self) -> f32 {
+        let influence =
+            (self.weight(Belief::GodControlsWeather) + self.weight(Belief::GodGrantsHarvest)) / 2.0;
+        (self.weight(Belief::GodExists) * (0.5 + influence / 2.0))
+            .max(0.0)
+            .min(1.0)
+    }
+
+    /// How much a human fears the god, from 0 to 1. Fearful people hoard.
+    pub fn fear(&self) -> f32 {
+        (self.weight(Belief::GodExists).max(0.0) * -self.weight(Belief::GodIsBenevolent))
+            .max(0.0)
+            .min(1.0)
+    }
+
+    /// How much a human credits their own labour over the god for the harvest, from 0 to 1.
+    /// Self-reliant people work longer.
+    pub fn self_reliance(&self) -> f32 {
+        (-self.weight(Belief::GodGrantsHarvest)).max(0.0).min(1.0)
+    }
+
+    pub fn description_lines(&self) -> Vec<String> {
+        let mut lines = vec!["Beliefs".to_string()];
+        for (belief, weight) in self.weights.iter() {
+            lines.push(format!("{}: {:+.2}", belief.label(), weight));
+        }
+        lines
+    }
+}
+
+impl Save for Beliefs {
+    fn save(&self, writer: &mut SaveWriter) {
+        writer.tag("beliefs");
+        writer.put(self.weights.len());
+        for (belief, weight) in self.weights.iter() {
+            writer.put(belief.name());
+            writer.put(weight);
+        }
+    }
+
+    fn load(reader: &mut SaveReader) -> Result<Beliefs, SaveError> {
+        reader.expect("beliefs")?;
+        let mut beliefs = Beliefs::new();
+        let len: usize = reader.get()?;
+        for _ in 0..len {
+            beliefs.weights.insert(reader.get()?, reader.get()?);
+        }
+        Ok(beliefs)
+    }
+}
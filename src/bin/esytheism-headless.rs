@@ -0,0 +1,100 @@
+use std::env;
+use std::process;
+
+use esytheism::simulation::Simulation;
+
+const DEFAULT_SCENARIO: &str = "static/data/test.scenario";
+const DEFAULT_TICKS: u64 = 60 * 24;
+
+fn usage() -> ! {
+    eprintln!("usage: esytheism-headless [--scenario <path> | --map <path>] [--ticks <n>] [--seed <n>] [--load <save>] [--save <save>]");
+    process::exit(2);
+}
+
+fn main() {
+    let mut scenario_path = DEFAULT_SCENARIO.to_string();
+    let mut map_path = None;
+    let mut ticks = DEFAULT_TICKS;
+    let mut seed = None;
+    let mut load_path = None;
+    let mut save_path = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--scenario" => scenario_path = args.next().unwrap_or_else(|| usage()),
+            "--map" => map_path = Some(args.next().unwrap_or_else(|| usage())),
+            "--ticks" => {
+                ticks = args
+                    .next()
+                    .and_then(|n| n.parse().ok())
+                    .unwrap_or_else(|| usage())
+            }
+            "--seed" => {
+                seed = Some(
+                    args.next()
+                        .and_then(|n| n.parse().ok())
+                        .unwrap_or_else(|| usage()),
+                )
+            }
+            "--load" => load_path = Some(args.next().unwrap_or_else(|| usage())),
+            "--save" => save_path = Some(args.next().unwrap_or_else(|| usage())),
+            _ => usage(),
+        }
+    }
+
+    let mut simulation = match (&load_path, &map_path) {
+        (Some(load_path), _) => Simulation::from_save_path(load_path).unwrap_or_else(|err| {
+            eprintln!("failed to load save {}: {}", load_path, err);
+            process::exit(1);
+        }),
+        (None, Some(map_path)) => {
+            // a bare map has nobody living on it, but is still useful for profiling
+            Simulation::from_map_path(map_path, seed.unwrap_or(0)).unwrap_or_else(|err| {
+                eprintln!("failed to load map {}: {}", map_path, err);
+                process::exit(1);
+            })
+        }
+        (None, None) => {
+            Simulation::from_scenario_path(&scenario_path, seed).unwrap_or_else(|err| {
+                eprintln!("failed to load scenario {}: {}", scenario_path, err);
+                process::exit(1);
+            })
+        }
+    };
+    simulation.run(ticks);
+
+    if let Some(save_path) = &save_path {
+        if let Err(err) = simulation.game_state.save_to_path(save_path) {
+            eprintln!("failed to save {}: {}", save_path, err);
+            process::exit(1);
+        }
+    }
+
+    let world = &simulation.game_state.world;
+    println!(
+        "{} ({} ticks)",
+        world.time.date_string(),
+        simulation.ticks()
+    );
+    println!(
+        "seed: {} fingerprint: {:016x}",
+        simulation.game_state.seed(),
+        world.fingerprint()
+    );
+    println!(
+        "divine power: {:.2}",
+        simulation.game_state.divine_power().amount()
+    );
+    for (human_id, human) in world.humans.iter() {
+        let mind = &simulation.game_state.minds[human_id];
+        println!("human {}", human_id);
+        for line in human
+            .description_lines(world)
+            .into_iter()
+            .chain(mind.description_lines())
+        {
+            println!("  {}", line);
+        }
+    }
+}
@@ -0,0 +1,161 @@
+use std::hash::{Hash, Hasher};
+
+use crate::job::Job;
+use crate::save::{Save, SaveError, SaveReader, SaveWriter};
+use crate::world::{Day, World};
+
+const BIOGRAPHY_LEN: usize = 100; // past this the oldest entries, after their birth, are forgotten
+
+/// Something that happened to someone worth remembering them by. Other people are named as
+/// they were at the time, since they may be long gone by the time anyone reads about it.
+#[derive(Clone, Hash)]
+pub enum LifeEvent {
+    Born,
+    CameOfAge,
+    GrewOld,
+    TookUpWork(Job),
+    MissedMeal,
+    GainedStores(usize), // how many containers their household took on
+    FellSick,
+    Injured,
+    Partnered(String),
+    HadChild(String),
+    Widowed(String),
+}
+
+/// The notable events of someone's life so far, oldest first.
+pub struct Biography {
+    entries: Vec<(Day, LifeEvent)>,
+}
+
+impl LifeEvent {
+    pub fn description(&self, world: &World) -> String {
+        match self {
+            LifeEvent::Born => "Born".to_string(),
+            LifeEvent::CameOfAge => "Came of age".to_string(),
+            LifeEvent::GrewOld => "Grew old".to_string(),
+            LifeEvent::TookUpWork(job) => format!("Worked as a {}", job.name(world)),
+            LifeEvent::MissedMeal => "Went hungry".to_string(),
+            LifeEvent::GainedStores(1) => "Took on a store".to_string(),
+            LifeEvent::GainedStores(count) => format!("Took on {} stores", count),
+            LifeEvent::FellSick => "Fell sick".to_string(),
+            LifeEvent::Injured => "Was injured".to_string(),
+            LifeEvent::Partnered(name) => format!("Settled down with {}", name),
+            LifeEvent::HadChild(name) => format!("Had a child, {}", name),
+            LifeEvent::Widowed(name) => format!("Lost {}", name),
+        }
+    }
+}
+
+impl Biography {
+    pub fn new() -> Biography {
+        Biography {
+            entries: Vec::new(),
+        }
+    }
+
+    /// Adds an entry. Going hungry more than once in a day is only worth mentioning once.
+    pub fn note(&mut self, day: Day, event: LifeEvent) {
+        if let (LifeEvent::MissedMeal, Some((last_day, LifeEvent::MissedMeal))) =
+            (&event, self.entries.last())
+        {
+            if *last_day == day {
+                return;
+            }
+        }
+        self.entries.push((day, event));
+        if self.entries.len() > BIOGRAPHY_LEN {
+            self.entries.remove(1);
+        }
+    }
+
+    pub fn entries(&self) -> &Vec<(Day, LifeEvent)> {
+        &self.entries
+    }
+
+    pub fn description_lines(&self, world: &World) -> Vec<String> {
+        self.entries
+            .iter()
+            .map(|(day, event)| format!("{}: {}", day.date_string(), event.description(world)))
+            .collect()
+    }
+}
+
+impl Hash for Biography {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.entries.hash(state);
+    }
+}
+
+impl Save for LifeEvent {
+    fn save(&self, writer: &mut SaveWriter) {
+        match self {
+            LifeEvent::Born => writer.put("born"),
+            LifeEvent::CameOfAge => writer.put("came_of_age"),
+            LifeEvent::GrewOld => writer.put("grew_old"),
+            LifeEvent::TookUpWork(job) => {
+                writer.put("took_up_work");
+                job.save(writer);
+            }
+            LifeEvent::MissedMeal => writer.put("missed_meal"),
+            LifeEvent::GainedStores(count) => {
+                writer.put("gained_stores");
+                writer.put(count);
+            }
+            LifeEvent::FellSick => writer.put("fell_sick"),
+            LifeEvent::Injured => writer.put("injured"),
+            LifeEvent::Partnered(name) => {
+                writer.put("partnered");
+                writer.put(name);
+            }
+            LifeEvent::HadChild(name) => {
+                writer.put("had_child");
+                writer.put(name);
+            }
+            LifeEvent::Widowed(name) => {
+                writer.put("widowed");
+                writer.put(name);
+            }
+        }
+    }
+
+    fn load(reader: &mut SaveReader) -> Result<LifeEvent, SaveError> {
+        let kind: String = reader.get()?;
+        Ok(match kind.as_str() {
+            "born" => LifeEvent::Born,
+            "came_of_age" => LifeEvent::CameOfAge,
+            "grew_old" => LifeEvent::GrewOld,
+            "took_up_work" => LifeEvent::TookUpWork(Job::load(reader)?),
+            "missed_meal" => LifeEvent::MissedMeal,
+            "gained_stores" => LifeEvent::GainedStores(reader.get()?),
+            "fell_sick" => LifeEvent::FellSick,
+            "injured" => LifeEvent::Injured,
+            "partnered" => LifeEvent::Partnered(reader.get()?),
+            "had_child" => LifeEvent::HadChild(reader.get()?),
+            "widowed" => LifeEvent::Widowed(reader.get()?),
+            _ => return Err(SaveError::Format(format!("unknown life event '{}'", kind))),
+        })
+    }
+}
+
+impl Save for Biography {
+    fn save(&self, writer: &mut SaveWriter) {
+        writer.tag("biography");
+        writer.put(self.entries.len());
+        for (day, event) in &self.entries {
+            writer.put(day);
+            event.save(writer);
+        }
+    }
+
+    fn load(reader: &mut SaveReader) -> Result<Biography, SaveError> {
+        reader.expect("biography")?;
+        let mut biography = Biography::new();
+        let len: usize = reader.get()?;
+        for _ in 0..len {
+            let day = reader.get()?;
+            biography.entries.push((day, LifeEvent::load(reader)?));
+        }
+        Ok(biography)
+    }
+}
@@ -0,0 +1,574 @@
+use std::error;
+use std::fmt;
+use std::str::{FromStr, SplitWhitespace};
+
+use crate::item::Item;
+use crate::save::{Save, SaveError, SaveReader, SaveWriter};
+
+/// Items the simulation itself refers to, which every catalogue must define. They always get
+/// the first indices, in this order, whatever order the file lists them in.
+const BUILT_IN: [&str; 7] = [
+    "Food",
+    "Money",
+    "Water",
+    "Wood",
+    "Alcohol",
+    "LuxuryGood",
+    "Waste",
+];
+
+/// Where the catalogue lives, relative to the folder holding the scenario or map.
+pub const CATALOGUE_FILE: &str = "items.catalogue";
+
+const DEFAULT_STACK: u32 = 100;
+const DEFAULT_HALF_STOCK: f32 = 10.0;
+
+#[derive(Copy, Clone, Eq, PartialEq, PartialOrd, Ord, Debug)]
+pub enum ItemTag {
+    Edible,    // can be eaten; the hungrier you are, the more it's worth
+    Drinkable, // quenches thirst; the thirstier you are, the more it's worth
+    Fuel,      // can be burnt
+    Currency,  // what prices are paid in; never loses its worth
+}
+
+/// Everything there is to know about one kind of item.
+pub struct ItemDefinition {
+    pub name: String,
+    pub weight: f32,
+    pub stack: u32,          // most that can be taken in one go
+    pub spoils: Option<u32>, // days it keeps for, if it goes off at all
+    pub worth: f32,          // in money, to someone who owns none
+    pub half_stock: f32,     // owning this many halves the worth of one more
+    pub tags: Vec<ItemTag>,
+}
+
+/// A way of turning some items into others, worked by a human at a workshop.
+pub struct Recipe {
+    pub name: String,
+    pub worker: String,           // what whoever works it is called
+    pub building: Option<String>, // the kind of workshop it needs, if any in particular
+    pub tool: Option<Item>,       // needed to work it, but not used up
+    pub labour: u32,              // ticks of work per batch
+    pub inputs: Vec<(Item, u32)>,
+    pub outputs: Vec<(Item, u32)>,
+}
+
+/// A kind of place to keep things in, which may slow down how fast they spoil.
+pub struct Storage {
+    pub name: String,
+    pub spoilage: f32, // days things age in it per day
+}
+
+/// The kinds of item in the world, the recipes for making them and the kinds of storage for
+/// keeping them, loaded from an `items.catalogue` file next to the scenario or map. One record
+/// per line, `#` starts a comment:
+///
+/// ```text
+/// item <Name> weight <w> [stack <n>] [spoils <days>] [worth <money>] [half_stock <n>] [edible] [drinkable] [fuel] [currency]
+/// recipe <name> worker <Title> labour <ticks> [building <kind>] [tool <Item>] [in <Item> <n>]... out <Item> <n>...
+/// storage <kind> spoilage <days per day>
+/// ```
+///
+/// The catalogue must define `Food`, `Money`, `Water`, `Wood`, `Alcohol`, `LuxuryGood` and
+/// `Waste` (what spoiled items turn into); any other item can be added without touching the
+/// code, and used in scenarios by name. Recipes may refer to items defined anywhere in the file.
+pub struct ItemCatalogue {
+    definitions: Vec<ItemDefinition>,
+    recipes: Vec<Recipe>,
+    storages: Vec<Storage>,
+}
+
+#[derive(Debug)]
+pub struct CatalogueError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for CatalogueError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl error::Error for CatalogueError {}
+
+impl FromStr for ItemTag {
+    type Err = ();
+
+    fn from_str(name: &str) -> Result<ItemTag, ()> {
+        match name {
+            "edible" => Ok(ItemTag::Edible),
+            "drinkable" => Ok(ItemTag::Drinkable),
+            "fuel" => Ok(ItemTag::Fuel),
+            "currency" => Ok(ItemTag::Currency),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for ItemTag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            ItemTag::Edible => "edible",
+            ItemTag::Drinkable => "drinkable",
+            ItemTag::Fuel => "fuel",
+            ItemTag::Currency => "currency",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl ItemDefinition {
+    pub fn has_tag(&self, tag: ItemTag) -> bool {
+        self.tags.contains(&tag)
+    }
+}
+
+impl ItemCatalogue {
+    pub fn parse(data: &str) -> Result<ItemCatalogue, CatalogueError> {
+        let mut parsed: Vec<ItemDefinition> = Vec::new();
+        let mut recipe_lines = Vec::new();
+        let mut storages: Vec<Storage> = Vec::new();
+        for (index, text) in data.lines().enumerate() {
+            let number = index + 1;
+            let error = |message: String| CatalogueError {
+                line: number,
+                message: message,
+            };
+            let mut tokens = text.split('#').next().unwrap_or("").split_whitespace();
+            match tokens.next() {
+                Some("item") => (),
+                // once every item is known
+                Some("recipe") => {
+                    recipe_lines.push((number, tokens));
+                    continue;
+                }
+                Some("storage") => {
+                    let name = tokens
+                        .next()
+                        .ok_or_else(|| error("missing storage name".to_string()))?;
+                    if storages.iter().any(|storage| storage.name == name) {
+                        return Err(error(format!("storage '{}' defined twice", name)));
+                    }
+                    let spoilage: f32 = match (tokens.next(), tokens.next()) {
+                        (Some("spoilage"), Some(value)) => value
+                            .parse()
+                            .map_err(|_| error(format!("invalid spoilage '{}'", value)))?,
+                        _ => return Err(error(format!("storage '{}' needs a spoilage", name))),
+                    };
+                    // anything less and what's kept there would get younger
+                    if !spoilage.is_finite() || spoilage < 0.0 {
+                        return Err(error(format!(
+                            "storage '{}' needs a spoilage of 0 or more",
+                            name
+                        )));
+                    }
+                    storages.push(Storage {
+                        name: name.to_string(),
+                        spoilage: spoilage,
+                    });
+                    continue;
+                }
+                Some(other) => return Err(error(format!("unknown record '{}'", other))),
+                None => continue,
+            }
+            let name = tokens
+                .next()
+                .ok_or_else(|| error("missing item name".to_string()))?;
+            if parsed.iter().any(|definition| definition.name == name) {
+                return Err(error(format!("item '{}' defined twice", name)));
+            }
+            let mut definition = ItemDefinition {
+                name: name.to_string(),
+                weight: -1.0,
+                stack: DEFAULT_STACK,
+                spoils: None,
+                worth: 0.0,
+                half_stock: DEFAULT_HALF_STOCK,
+                tags: Vec::new(),
+            };
+            while let Some(key) = tokens.next() {
+                if let Ok(tag) = key.parse() {
+                    definition.tags.push(tag);
+                    continue;
+                }
+                let value = tokens
+                    .next()
+                    .ok_or_else(|| error(format!("missing {}", key)))?;
+                let invalid = || error(format!("invalid {} '{}'", key, value));
+                match key {
+                    "weight" => definition.weight = value.parse().map_err(|_| invalid())?,
+                    "stack" => definition.stack = value.parse().map_err(|_| invalid())?,
+                    "spoils" => definition.spoils = Some(value.parse().map_err(|_| invalid())?),
+                    "worth" => definition.worth = value.parse().map_err(|_| invalid())?,
+                    "half_stock" => definition.half_stock = value.parse().map_err(|_| invalid())?,
+                    _ => return Err(error(format!("unknown item field '{}'", key))),
+                }
+            }
+            if !definition.weight.is_finite() || definition.weight <= 0.0 {
+                return Err(error(format!("item '{}' needs a positive weight", name)));
+            }
+            if !definition.worth.is_finite() || definition.worth < 0.0 {
+                return Err(error(format!(
+                    "item '{}' can't be worth less than nothing",
+                    name
+                )));
+            }
+            if !definition.half_stock.is_finite() || definition.half_stock <= 0.0 {
+                return Err(error(format!(
+                    "item '{}' needs a positive half_stock",
+                    name
+                )));
+            }
+            if definition.stack == 0 {
+                return Err(error(format!(
+                    "item '{}' needs a stack of at least 1",
+                    name
+                )));
+            }
+            parsed.push(definition);
+        }
+
+        let mut definitions = Vec::with_capacity(parsed.len());
+        for name in BUILT_IN.iter() {
+            match parsed
+                .iter()
+                .position(|definition| definition.name == *name)
+            {
+                Some(index) => definitions.push(parsed.remove(index)),
+                None => {
+                    return Err(CatalogueError {
+                        line: data.lines().count(),
+                        message: format!("missing built-in item '{}'", name),
+                    })
+                }
+            }
+        }
+        definitions.extend(parsed);
+        if definitions.len() > u16::max_value() as usize {
+            return Err(CatalogueError {
+                line: data.lines().count(),
+                message: "too many items".to_string(),
+            });
+        }
+        let mut catalogue = ItemCatalogue {
+            definitions: definitions,
+            recipes: Vec::new(),
+            storages: storages,
+        };
+        for (number, tokens) in recipe_lines {
+            let recipe = catalogue.parse_recipe(number, tokens)?;
+            catalogue.recipes.push(recipe);
+        }
+        Ok(catalogue)
+    }
+
+    fn parse_recipe(
+        &self,
+        number: usize,
+        mut tokens: SplitWhitespace,
+    ) -> Result<Recipe, CatalogueError> {
+        let error = |message: String| CatalogueError {
+            line: number,
+            message: message,
+        };
+        let name = tokens
+            .next()
+            .ok_or_else(|| error("missing recipe name".to_string()))?;
+        if self.find_recipe(name).is_some() {
+            return Err(error(format!("recipe '{}' defined twice", name)));
+        }
+        let mut recipe = Recipe {
+            name: name.to_string(),
+            worker: String::new(),
+            building: None,
+            tool: None,
+            labour: 0,
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+        };
+        while let Some(key) = tokens.next() {
+            let value = tokens
+                .next()
+                .ok_or_else(|| error(format!("missing {}", key)))?;
+            let item = |name: &str| {
+                self.find(name)
+                    .ok_or_else(|| error(format!("unknown item '{}'", name)))
+            };
+            let count = |tokens: &mut SplitWhitespace| {
+                let count = tokens
+                    .next()
+                    .ok_or_else(|| error(format!("missing {} count", value)))?;
+                match count.parse() {
+                    Ok(0) => Err(error(format!("{} count must be at least 1", value))),
+                    Ok(count) => Ok(count),
+                    Err(_) => Err(error(format!("invalid {} count '{}'", value, count))),
+                }
+            };
+            match key {
+                "worker" => recipe.worker = value.to_string(),
+                "labour" => {
+                    recipe.labour = value
+                        .parse()
+                        .map_err(|_| error(format!("invalid labour '{}'", value)))?
+                }
+                "building" => recipe.building = Some(value.to_string()),
+                "tool" => recipe.tool = Some(item(value)?),
+                "in" => recipe.inputs.push((item(value)?, count(&mut tokens)?)),
+                "out" => recipe.outputs.push((item(value)?, count(&mut tokens)?)),
+                _ => return Err(error(format!("unknown recipe field '{}'", key))),
+            }
+        }
+        if recipe.worker.is_empty() {
+            return Err(error(format!("recipe '{}' has no worker", name)));
+        }
+        if recipe.labour == 0 {
+            return Err(error(format!("recipe '{}' needs some labour", name)));
+        }
+        if recipe.outputs.is_empty() {
+            return Err(error(format!("recipe '{}' makes nothing", name)));
+        }
+        Ok(recipe)
+    }
+
+    pub fn get(&self, item: Item) -> &ItemDefinition {
+        &self.definitions[item.index()]
+    }
+
+    pub fn name(&self, item: Item) -> &str {
+        &self.get(item).name
+    }
+
+    pub fn weight(&self, item: Item) -> f32 {
+        self.get(item).weight
+    }
+
+    pub fn find(&self, name: &str) -> Option<Item> {
+        self.definitions
+            .iter()
+            .position(|definition| definition.name == name)
+            .map(|index| Item::from_index(index))
+    }
+
+    pub fn recipe(&self, index: usize) -> &Recipe {
+        &self.recipes[index]
+    }
+
+    pub fn recipe_count(&self) -> usize {
+        self.recipes.len()
+    }
+
+    pub fn find_recipe(&self, name: &str) -> Option<usize> {
+        self.recipes.iter().position(|recipe| recipe.name == name)
+    }
+
+    pub fn storage(&self, name: &str) -> Option<&Storage> {
+        self.storages.iter().find(|storage| storage.name == name)
+    }
+
+    /// Every kind of item, in catalogue order.
+    pub fn items(&self) -> impl Iterator<Item = Item> {
+        (0..self.definitions.len()).map(Item::from_index)
+    }
+
+    /// Everything that can be bought and sold, in catalogue order.
+    pub fn goods<'a>(&'a self) -> impl Iterator<Item = Item> + 'a {
+        self.items()
+            .filter(move |item| !self.get(*item).has_tag(ItemTag::Currency))
+    }
+
+    pub fn tagged<'a>(&'a self, tag: ItemTag) -> impl Iterator<Item = Item> + 'a {
+        self.items()
+            .filter(move |item| self.get(*item).has_tag(tag))
+    }
+}
+
+impl Save for ItemDefinition {
+    fn save(&self, writer: &mut SaveWriter) {
+        writer.tag("item");
+        writer.put(&self.name);
+        writer.put(self.weight);
+        writer.put(self.stack);
+        writer.put_option(self.spoils);
+        writer.put(self.worth);
+        writer.put(self.half_stock);
+        writer.put(self.tags.len());
+        for tag in self.tags.iter() {
+            writer.put(tag);
+        }
+    }
+
+    fn load(reader: &mut SaveReader) -> Result<ItemDefinition, SaveError> {
+        reader.expect("item")?;
+        let mut definition = ItemDefinition {
+            name: reader.get()?,
+            weight: reader.get()?,
+            stack: reader.get()?,
+            spoils: reader.get_option()?,
+            worth: reader.get()?,
+            half_stock: reader.get()?,
+            tags: Vec::new(),
+        };
+        let len: usize = reader.get()?;
+        for _ in 0..len {
+            definition.tags.push(reader.get()?);
+        }
+        Ok(definition)
+    }
+}
+
+impl Save for Recipe {
+    fn save(&self, writer: &mut SaveWriter) {
+        writer.tag("recipe");
+        writer.put(&self.name);
+        writer.put(&self.worker);
+        writer.put_option(self.building.as_ref());
+        writer.put_option(self.tool);
+        writer.put(self.labour);
+        for items in [&self.inputs, &self.outputs].iter() {
+            writer.put(items.len());
+            for (item, count) in items.iter() {
+                writer.put(item);
+                writer.put(count);
+            }
+        }
+    }
+
+    fn load(reader: &mut SaveReader) -> Result<Recipe, SaveError> {
+        reader.expect("recipe")?;
+        let mut recipe = Recipe {
+            name: reader.get()?,
+            worker: reader.get()?,
+            building: reader.get_option()?,
+            tool: reader.get_option()?,
+            labour: reader.get()?,
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+        };
+        for items in [&mut recipe.inputs, &mut recipe.outputs].iter_mut() {
+            let len: usize = reader.get()?;
+            for _ in 0..len {
+                items.push((reader.get()?, reader.get()?));
+            }
+        }
+        Ok(recipe)
+    }
+}
+
+impl Save for Storage {
+    fn save(&self, writer: &mut SaveWriter) {
+        writer.tag("storage");
+        writer.put(&self.name);
+        writer.put(self.spoilage);
+    }
+
+    fn load(reader: &mut SaveReader) -> Result<Storage, SaveError> {
+        reader.expect("storage")?;
+        Ok(Storage {
+            name: reader.get()?,
+            spoilage: reader.get()?,
+        })
+    }
+}
+
+impl Save for ItemCatalogue {
+    fn save(&self, writer: &mut SaveWriter) {
+        writer.put_list("catalogue", &self.definitions);
+        writer.put_list("recipes", &self.recipes);
+        writer.put_list("storages", &self.storages);
+    }
+
+    fn load(reader: &mut SaveReader) -> Result<ItemCatalogue, SaveError> {
+        let definitions: Vec<ItemDefinition> = reader.get_list("catalogue")?;
+        if definitions.len() < BUILT_IN.len() {
+            return Err(SaveError::Format(
+                "catalogue is missing built-in items".to_string(),
+            ));
+        }
+        Ok(ItemCatalogue {
+            definitions: definitions,
+            recipes: reader.get_list("recipes")?,
+            storages: reader.get_list("storages")?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // just the built-in items, on lines 1 to 7
+    const BUILT_INS: &str = "item Food weight 1 spoils 7\n\
+                             item Money weight 0.1 currency\n\
+                             item Water weight 1 drinkable\n\
+                             item Wood weight 2\n\
+                             item Alcohol weight 1\n\
+                             item LuxuryGood weight 0.5\n\
+                             item Waste weight 1\n";
+
+    // what's wrong with `line`, added after the built-ins
+    fn error(line: &str) -> String {
+        match ItemCatalogue::parse(&format!("{}{}\n", BUILT_INS, line)) {
+            Ok(_) => panic!("'{}' should have been rejected", line),
+            Err(err) => {
+                assert_eq!(err.line, 8);
+                err.message
+            }
+        }
+    }
+
+    #[test]
+    fn shipped_catalogue_parses() {
+        let items = ItemCatalogue::parse(include_str!("../static/data/items.catalogue")).unwrap();
+        assert_eq!(items.name(Item::FOOD), "Food");
+        assert!(items.find("Food") == Some(Item::FOOD));
+        assert!(items.find("Tools").is_some());
+        assert!(items.recipe_count() > 0);
+        assert!(items.find_recipe("brew").is_some());
+    }
+
+    #[test]
+    fn bad_weights_rejected() {
+        assert_eq!(
+            error("item Grain weight NaN"),
+            "item 'Grain' needs a positive weight"
+        );
+        assert_eq!(
+            error("item Grain weight 0"),
+            "item 'Grain' needs a positive weight"
+        );
+        assert_eq!(error("item Grain"), "item 'Grain' needs a positive weight");
+    }
+
+    #[test]
+    fn bad_half_stock_rejected() {
+        assert_eq!(
+            error("item Grain weight 1 half_stock 0"),
+            "item 'Grain' needs a positive half_stock"
+        );
+    }
+
+    #[test]
+    fn negative_spoilage_rejected() {
+        assert_eq!(
+            error("storage pit spoilage -0.5"),
+            "storage 'pit' needs a spoilage of 0 or more"
+        );
+        assert_eq!(
+            error("storage pit spoilage NaN"),
+            "storage 'pit' needs a spoilage of 0 or more"
+        );
+    }
+
+    #[test]
+    fn zero_recipe_counts_rejected() {
+        assert_eq!(
+            error("recipe mash worker Brewer labour 10 in Food 0 out Alcohol 1"),
+            "Food count must be at least 1"
+        );
+        assert_eq!(
+            error("recipe mash worker Brewer labour 10 in Food 2 out Alcohol 0"),
+            "Alcohol count must be at least 1"
+        );
+    }
+}
@@ -0,0 +1,518 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::ops::{Index, IndexMut};
+use std::str::FromStr;
+
+use rayon::prelude::*;
+
+use crate::save::{Save, SaveError, SaveReader, SaveWriter};
+
+/// Refers to one entity in an `Arena<T>`. A slot freed by removing an entity gets a new
+/// generation before it's reused, so handles to whatever used to be there stop working instead
+/// of quietly pointing at its replacement.
+pub struct Handle<T> {
+    index: u32,
+    generation: u32,
+    marker: PhantomData<fn() -> T>,
+}
+
+/// Entities of one kind, which can be added and removed in any order. Handles stay valid until
+/// their entity is removed.
+pub struct Arena<T> {
+    slots: Vec<Slot<T>>,
+}
+
+struct Slot<T> {
+    generation: u32,
+    value: Option<T>,
+}
+
+/// Something kept for each entity of an arena, outside it: a human's mind, an inventory's
+/// mailbox. Looked up by the same handles, and just as safe to use with stale ones.
+pub struct HandleMap<T, V> {
+    slots: Vec<Option<(u32, V)>>, // generation of the handle it was inserted under
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Handle<T> {
+    fn new(index: usize, generation: u32) -> Handle<T> {
+        Handle {
+            index: index as u32,
+            generation: generation,
+            marker: PhantomData,
+        }
+    }
+
+    /// Position in the arena. Unique among living entities, but reused after removal.
+    pub fn index(&self) -> usize {
+        self.index as usize
+    }
+}
+
+// written out rather than derived, which would needlessly require the same of `T`
+impl<T> Copy for Handle<T> {}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Handle<T> {
+        *self
+    }
+}
+
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Handle<T>) -> bool {
+        self.index == other.index && self.generation == other.generation
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> PartialOrd for Handle<T> {
+    fn partial_cmp(&self, other: &Handle<T>) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for Handle<T> {
+    fn cmp(&self, other: &Handle<T>) -> Ordering {
+        (self.index, self.generation).cmp(&(other.index, other.generation))
+    }
+}
+
+impl<T> Hash for Handle<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.index.hash(state);
+        self.generation.hash(state);
+    }
+}
+
+impl<T> fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+impl<T> fmt::Display for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}v{}", self.index, self.generation)
+    }
+}
+
+impl<T> FromStr for Handle<T> {
+    type Err = ();
+
+    fn from_str(handle: &str) -> Result<Handle<T>, ()> {
+        let mut parts = handle.splitn(2, 'v');
+        let index: u32 = parts.next().ok_or(())?.parse().map_err(|_| ())?;
+        let generation = parts.next().ok_or(())?.parse().map_err(|_| ())?;
+        Ok(Handle::new(index as usize, generation))
+    }
+}
+
+impl<T> Arena<T> {
+    pub fn new() -> Arena<T> {
+        Arena { slots: Vec::new() }
+    }
+
+    /// Reuses the lowest free slot, so where things end up depends only on what's in the arena
+    /// and not on the order they left it.
+    pub fn insert(&mut self, value: T) -> Handle<T> {
+        match self.slots.iter().position(|slot| slot.value.is_none()) {
+            Some(index) => {
+                let slot = &mut self.slots[index];
+                slot.value = Some(value);
+                Handle::new(index, slot.generation)
+            }
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    value: Some(value),
+                });
+                Handle::new(self.slots.len() - 1, 0)
+            }
+        }
+    }
+
+    /// Takes the entity out, if it's still there. Its handles are dangling from then on.
+    pub fn remove(&mut self, handle: Handle<T>) -> Option<T> {
+        match self.slots.get_mut(handle.index()) {
+            Some(slot) if slot.generation == handle.generation && slot.value.is_some() => {
+                slot.generation += 1;
+                slot.value.take()
+            }
+            _ => None,
+        }
+    }
+
+    pub fn get(&self, handle: Handle<T>) -> Option<&T> {
+        match self.slots.get(handle.index()) {
+            Some(slot) if slot.generation == handle.generation => slot.value.as_ref(),
+            _ => None,
+        }
+    }
+
+    pub fn get_mut(&mut self, handle: Handle<T>) -> Option<&mut T> {
+        match self.slots.get_mut(handle.index()) {
+            Some(slot) if slot.generation == handle.generation => slot.value.as_mut(),
+            _ => None,
+        }
+    }
+
+    pub fn contains(&self, handle: Handle<T>) -> bool {
+        self.get(handle).is_some()
+    }
+
+    /// How many entities are living, not how many slots there are.
+    pub fn len(&self) -> usize {
+        self.slots
+            .iter()
+            .filter(|slot| slot.value.is_some())
+            .count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// In slot order, which is the same on every run.
+    pub fn iter(&self) -> impl Iterator<Item = (Handle<T>, &T)> {
+        self.slots.iter().enumerate().filter_map(|(index, slot)| {
+            slot.value
+                .as_ref()
+                .map(|value| (Handle::new(index, slot.generation), value))
+        })
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Handle<T>, &mut T)> {
+        self.slots
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(index, slot)| {
+                let generation = slot.generation;
+                slot.value
+                    .as_mut()
+                    .map(|value| (Handle::new(index, generation), value))
+            })
+    }
+
+    pub fn handles<'a>(&'a self) -> impl Iterator<Item = Handle<T>> + 'a {
+        self.iter().map(|(handle, _)| handle)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.slots.iter().filter_map(|slot| slot.value.as_ref())
+    }
+}
+
+impl<T: Send> Arena<T> {
+    pub fn par_iter_mut(&mut self) -> impl ParallelIterator<Item = (Handle<T>, &mut T)> {
+        self.slots
+            .par_iter_mut()
+            .enumerate()
+            .filter_map(|(index, slot)| {
+                let generation = slot.generation;
+                slot.value
+                    .as_mut()
+                    .map(|value| (Handle::new(index, generation), value))
+            })
+    }
+
+    /// Every entity together with what's kept for it in `map`. Entities with nothing kept, and
+    /// anything kept for entities that have gone, are skipped.
+    pub fn par_zip_mut<'a, V: Send>(
+        &'a mut self,
+        map: &'a mut HandleMap<T, V>,
+    ) -> impl ParallelIterator<Item = (Handle<T>, &'a mut T, &'a mut V)> {
+        self.slots
+            .par_iter_mut()
+            .zip(map.slots.par_iter_mut())
+            .enumerate()
+            .filter_map(
+                |(index, (slot, entry))| match (slot.value.as_mut(), entry) {
+                    (Some(value), Some((generation, other))) if *generation == slot.generation => {
+                        Some((Handle::new(index, *generation), value, other))
+                    }
+                    _ => None,
+                },
+            )
+    }
+}
+
+impl<T> Index<Handle<T>> for Arena<T> {
+    type Output = T;
+
+    /// Panics on a dangling handle: use `get` wherever the entity might have gone.
+    fn index(&self, handle: Handle<T>) -> &T {
+        self.get(handle)
+            .unwrap_or_else(|| panic!("dangling handle {}", handle))
+    }
+}
+
+impl<T> IndexMut<Handle<T>> for Arena<T> {
+    fn index_mut(&mut self, handle: Handle<T>) -> &mut T {
+        self.get_mut(handle)
+            .unwrap_or_else(|| panic!("dangling handle {}", handle))
+    }
+}
+
+// free slots count too, since their generations decide the handles given out next
+impl<T: Hash> Hash for Arena<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for slot in &self.slots {
+            slot.generation.hash(state);
+            slot.value.hash(state);
+        }
+    }
+}
+
+impl<T: Save> Arena<T> {
+    pub fn save(&self, tag: &str, writer: &mut SaveWriter) {
+        writer.tag(tag);
+        writer.put(self.slots.len());
+        for slot in &self.slots {
+            writer.tag("slot");
+            writer.put(slot.generation);
+            match &slot.value {
+                Some(value) => {
+                    writer.put("+");
+                    value.save(writer);
+                }
+                None => writer.put("-"),
+            }
+        }
+    }
+
+    pub fn load(tag: &str, reader: &mut SaveReader) -> Result<Arena<T>, SaveError> {
+        reader.expect(tag)?;
+        let len: usize = reader.get()?;
+        let mut slots = Vec::new();
+        for _ in 0..len {
+            reader.expect("slot")?;
+            let generation = reader.get()?;
+            let occupied: String = reader.get()?;
+            let value = match occupied.as_str() {
+                "+" => Some(T::load(reader)?),
+                "-" => None,
+                _ => {
+                    return Err(SaveError::Format(format!(
+                        "expected '+' or '-' but found '{}'",
+                        occupied
+                    )))
+                }
+            };
+            slots.push(Slot {
+                generation: generation,
+                value: value,
+            });
+        }
+        Ok(Arena { slots: slots })
+    }
+}
+
+impl<T, V> HandleMap<T, V> {
+    pub fn new() -> HandleMap<T, V> {
+        HandleMap {
+            slots: Vec::new(),
+            marker: PhantomData,
+        }
+    }
+
+    /// Replaces whatever was kept for the slot before, even under an older handle.
+    pub fn insert(&mut self, handle: Handle<T>, value: V) {
+        let index = handle.index();
+        while self.slots.len() <= index {
+            self.slots.push(None);
+        }
+        self.slots[index] = Some((handle.generation, value));
+    }
+
+    pub fn remove(&mut self, handle: Handle<T>) -> Option<V> {
+        match self.slots.get_mut(handle.index()) {
+            Some(entry)
+                if entry.as_ref().map(|(generation, _)| *generation) == Some(handle.generation) =>
+            {
+                entry.take().map(|(_, value)| value)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn get(&self, handle: Handle<T>) -> Option<&V> {
+        match self.slots.get(handle.index()) {
+            Some(Some((generation, value))) if *generation == handle.generation => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn get_mut(&mut self, handle: Handle<T>) -> Option<&mut V> {
+        match self.slots.get_mut(handle.index()) {
+            Some(Some((generation, value))) if *generation == handle.generation => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.iter().filter(|entry| entry.is_some()).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Handle<T>, &V)> {
+        self.slots.iter().enumerate().filter_map(|(index, entry)| {
+            entry
+                .as_ref()
+                .map(|(generation, value)| (Handle::new(index, *generation), value))
+        })
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.slots
+            .iter()
+            .filter_map(|entry| entry.as_ref().map(|(_, value)| value))
+    }
+}
+
+impl<T, V: Send> HandleMap<T, V> {
+    pub fn par_iter_mut(&mut self) -> impl ParallelIterator<Item = (Handle<T>, &mut V)> {
+        self.slots
+            .par_iter_mut()
+            .enumerate()
+            .filter_map(|(index, entry)| {
+                entry
+                    .as_mut()
+                    .map(|(generation, value)| (Handle::new(index, *generation), value))
+            })
+    }
+}
+
+impl<T, V: Clone> Clone for HandleMap<T, V> {
+    fn clone(&self) -> HandleMap<T, V> {
+        HandleMap {
+            slots: self.slots.clone(),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T, V> Index<Handle<T>> for HandleMap<T, V> {
+    type Output = V;
+
+    fn index(&self, handle: Handle<T>) -> &V {
+        self.get(handle)
+            .unwrap_or_else(|| panic!("nothing kept for handle {}", handle))
+    }
+}
+
+impl<T, V> IndexMut<Handle<T>> for HandleMap<T, V> {
+    fn index_mut(&mut self, handle: Handle<T>) -> &mut V {
+        self.get_mut(handle)
+            .unwrap_or_else(|| panic!("nothing kept for handle {}", handle))
+    }
+}
+
+impl<T, V: Save> HandleMap<T, V> {
+    pub fn save(&self, tag: &str, writer: &mut SaveWriter) {
+        writer.tag(tag);
+        writer.put(self.slots.len());
+        for entry in &self.slots {
+            writer.tag("slot");
+            match entry {
+                Some((generation, value)) => {
+                    writer.put(generation);
+                    value.save(writer);
+                }
+                None => writer.put("-"),
+            }
+        }
+    }
+
+    pub fn load(tag: &str, reader: &mut SaveReader) -> Result<HandleMap<T, V>, SaveError> {
+        reader.expect(tag)?;
+        let len: usize = reader.get()?;
+        let mut map = HandleMap::new();
+        for _ in 0..len {
+            reader.expect("slot")?;
+            let entry = match reader.get_option()? {
+                Some(generation) => Some((generation, V::load(reader)?)),
+                None => None,
+            };
+            map.slots.push(entry);
+        }
+        Ok(map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Thing(u32);
+
+    impl Save for Thing {
+        fn save(&self, writer: &mut SaveWriter) {
+            writer.tag("thing");
+            writer.put(self.0);
+        }
+
+        fn load(reader: &mut SaveReader) -> Result<Thing, SaveError> {
+            Ok(Thing(reader.get_tagged("thing")?))
+        }
+    }
+
+    #[test]
+    fn removed_handle_fails() {
+        let mut arena = Arena::new();
+        let handle = arena.insert(Thing(1));
+        assert_eq!(arena.remove(handle), Some(Thing(1)));
+        assert_eq!(arena.get(handle), None);
+        assert_eq!(arena.remove(handle), None);
+        assert!(arena.is_empty());
+    }
+
+    #[test]
+    fn reused_slot_rejects_old_handle() {
+        let mut arena = Arena::new();
+        let old = arena.insert(Thing(1));
+        arena.remove(old);
+        let new = arena.insert(Thing(2));
+        assert_eq!(new.index(), old.index());
+        assert_ne!(new, old);
+        assert_eq!(arena.get(old), None);
+        assert_eq!(arena.get(new), Some(&Thing(2)));
+
+        let mut map = HandleMap::new();
+        map.insert(old, "old");
+        map.insert(new, "new");
+        assert_eq!(map.get(old), None);
+        assert_eq!(map.get(new), Some(&"new"));
+    }
+
+    #[test]
+    fn save_and_load_keep_generations() {
+        let mut arena = Arena::new();
+        let gone = arena.insert(Thing(1));
+        let kept = arena.insert(Thing(2));
+        arena.remove(gone);
+        let reused = arena.insert(Thing(3));
+        let freed = arena.insert(Thing(4));
+        arena.remove(freed);
+
+        let mut writer = SaveWriter::new();
+        arena.save("things", &mut writer);
+        let data = writer.finish();
+        let mut loaded: Arena<Thing> = Arena::load("things", &mut SaveReader::new(&data)).unwrap();
+
+        assert_eq!(loaded.get(gone), None);
+        assert_eq!(loaded.get(kept), Some(&Thing(2)));
+        assert_eq!(loaded.get(reused), Some(&Thing(3)));
+        assert_eq!(loaded.get(freed), None);
+        // the freed slot is handed out under a newer generation than before saving
+        let next = loaded.insert(Thing(5));
+        assert_eq!(next.index(), freed.index());
+        assert_ne!(next, freed);
+    }
+}
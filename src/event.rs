@@ -0,0 +1,132 @@
+use std::hash::{Hash, Hasher};
+
+use quicksilver::geom::Vector;
+
+use crate::catalogue::ItemCatalogue;
+use crate::human::Harm;
+use crate::miracle::Miracle;
+use crate::plant::CropId;
+use crate::save::{Save, SaveError, SaveReader, SaveWriter};
+
+/// Something that happened in the world which humans might notice and draw conclusions from.
+#[derive(Copy, Clone, PartialEq, Hash)]
+pub enum WorldEvent {
+    CropFailed(CropId),       // had sun but nothing to drink
+    GoodHarvest(CropId, u32), // food grown
+    Rain(u32),
+    Miracle(Miracle),
+    Birth,
+    Death(Harm), // what they died of
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub struct EventRecord {
+    pub tick: u64,
+    pub location: Option<Vector>, // None for things everyone notices, like the weather
+    pub event: WorldEvent,
+}
+
+impl WorldEvent {
+    pub fn crop_id(&self) -> Option<CropId> {
+        match self {
+            WorldEvent::CropFailed(crop_id)
+            | WorldEvent::GoodHarvest(crop_id, _)
+            | WorldEvent::Miracle(Miracle::BlessCrop(crop_id)) => Some(*crop_id),
+            _ => None,
+        }
+    }
+}
+
+impl EventRecord {
+    pub fn new(tick: u64, location: Option<Vector>, event: WorldEvent) -> EventRecord {
+        EventRecord {
+            tick: tick,
+            location: location,
+            event: event,
+        }
+    }
+
+    pub fn description(&self, items: &ItemCatalogue) -> String {
+        match self.event {
+            WorldEvent::CropFailed(crop_id) => format!("Crop {} withered", crop_id.index()),
+            WorldEvent::GoodHarvest(crop_id, growth) => {
+                format!("Crop {} grew {} food", crop_id.index(), growth)
+            }
+            WorldEvent::Rain(rain) => format!("It rained ({})", rain),
+            WorldEvent::Miracle(miracle) => miracle.description(items),
+            WorldEvent::Birth => "A child was born".to_string(),
+            WorldEvent::Death(harm) => format!("Someone died of {}", harm.label()),
+        }
+    }
+}
+
+impl Hash for EventRecord {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.tick.hash(state);
+        if let Some(location) = self.location {
+            location.x.to_bits().hash(state);
+            location.y.to_bits().hash(state);
+        }
+        self.event.hash(state);
+    }
+}
+
+impl Save for WorldEvent {
+    fn save(&self, writer: &mut SaveWriter) {
+        match self {
+            WorldEvent::CropFailed(crop_id) => {
+                writer.put("crop_failed");
+                writer.put(crop_id);
+            }
+            WorldEvent::GoodHarvest(crop_id, growth) => {
+                writer.put("good_harvest");
+                writer.put(crop_id);
+                writer.put(growth);
+            }
+            WorldEvent::Rain(rain) => {
+                writer.put("rain");
+                writer.put(rain);
+            }
+            WorldEvent::Miracle(miracle) => {
+                writer.put("miracle");
+                miracle.save(writer);
+            }
+            WorldEvent::Birth => writer.put("birth"),
+            WorldEvent::Death(harm) => {
+                writer.put("death");
+                writer.put(harm);
+            }
+        }
+    }
+
+    fn load(reader: &mut SaveReader) -> Result<WorldEvent, SaveError> {
+        let kind: String = reader.get()?;
+        Ok(match kind.as_str() {
+            "crop_failed" => WorldEvent::CropFailed(reader.get()?),
+            "good_harvest" => WorldEvent::GoodHarvest(reader.get()?, reader.get()?),
+            "rain" => WorldEvent::Rain(reader.get()?),
+            "miracle" => WorldEvent::Miracle(Miracle::load(reader)?),
+            "birth" => WorldEvent::Birth,
+            "death" => WorldEvent::Death(reader.get()?),
+            _ => return Err(SaveError::Format(format!("unknown event '{}'", kind))),
+        })
+    }
+}
+
+impl Save for EventRecord {
+    fn save(&self, writer: &mut SaveWriter) {
+        writer.tag("event");
+        writer.put(self.tick);
+        writer.put_option_vector(self.location);
+        self.event.save(writer);
+    }
+
+    fn load(reader: &mut SaveReader) -> Result<EventRecord, SaveError> {
+        reader.expect("event")?;
+        Ok(EventRecord::new(
+            reader.get()?,
+            reader.get_option_vector()?,
+            WorldEvent::load(reader)?,
+        ))
+    }
+}
@@ -6,131 +6,901 @@ use quicksilver::{
     graphics::{Background::Col, Background::Img, Color, Font, FontStyle, Image},
     input::{ButtonState, Key},
     lifecycle::{run, Asset, Event, Settings, State, Window},
-    load_file, Future, Result,
+    Result,
 };
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
 use std::fs::File;
 use std::io::prelude::*;
 use std::io::BufReader;
 use std::num::Wrapping;
+use std::path::Path;
 use std::sync::mpsc::{channel, Receiver, Sender};
 
 use std::u32;
 
 use rand::prelude::*;
 
-use crate::geography::Geography;
-use crate::human::{Human, Job, Mind};
-use crate::item::{Inventory, Item, ItemMessage};
+use crate::biography::LifeEvent;
+use crate::catalogue::ItemCatalogue;
+use crate::entity::{Arena, HandleMap};
+use crate::event::{EventRecord, WorldEvent};
+use crate::geography::{Geography, TilePoint};
+use crate::household::{Household, HouseholdId};
+use crate::human::{Human, HumanId, LifeStage, Mind, DAYS_PER_YEAR};
+use crate::item::{Inventory, InventoryId, Item, ItemMessage, Senders};
+use crate::market::{Market, MarketMessage, STALL_CAPACITY};
+use crate::miracle::{Miracle, MiracleError, EXHAUSTION, MIRACLE_WEATHER};
+use crate::personality::Personality;
 use crate::plant::Crop;
+use crate::power::{DivinePower, InsufficientPower};
+use crate::random::{
+    stream_rng, FAMILY_STREAM, HEALTH_STREAM, MIND_STREAM, NAME_STREAM, PERSONALITY_STREAM,
+    SOCIAL_STREAM, WEATHER_STREAM,
+};
+use crate::relationship::Contact;
+use crate::save::{Save, SaveError, SaveReader, SaveWriter, SAVE_MAGIC, SAVE_VERSION};
+use crate::scenario::{Scenario, DEFAULT_NAMES};
 use crate::weather::Weather;
-use crate::world::{Container, Time, World};
+use crate::world::{
+    Bed, Container, ContainerId, Shrine, Time, Well, Workshop, World, TICKS_PER_DAY,
+};
+
+const PARTNERING_CHANCE: f64 = 0.05; // per day, for an adult with someone single nearby
+const MEETING_DISTANCE: f32 = 10.0;
+const BIRTH_CHANCE: f64 = 1.0 / 360.0; // per day, for a couple who can feed a child
+const CHILD_FOOD: u32 = 100; // what a couple needs in store before having a child
+const CHILD_CAPACITY: f32 = 30.0;
+// per tick, for two humans of average sociability awake on neighbouring tiles
+const CONVERSATION_CHANCE: f64 = 0.01;
+const VISITING_CHATTER: f64 = 10.0; // times likelier to talk when one has come to see the other
+
+// how much two people can differ, in temperament or in what they make of the god, before their
+// chats start turning into rows; past that, each bit more makes a row likelier
+const TOLERANCE: f32 = 0.25;
+const QUARRELSOMENESS: f32 = 1.0;
 
 pub struct GameState {
     pub world: World,
-    pub minds: Vec<Mind>,
-    inventory_senders: Vec<Sender<ItemMessage>>,
-    inventory_receivers: Vec<Receiver<ItemMessage>>,
+    pub minds: HandleMap<Human, Mind>,
+    seed: u64,
+    names: Vec<String>, // for children yet to be born
+    divine_power: DivinePower,
+    inventory_senders: Senders,
+    inventory_receivers: HandleMap<Inventory, Receiver<ItemMessage>>,
+    market_sender: Sender<MarketMessage>,
+    market_receiver: Receiver<MarketMessage>,
 }
 
 impl GameState {
-    pub fn new() -> GameState {
-        let geo = load_file("data/test.map")
-            .map(|data| Geography::from_data(40, 30, &data))
-            .wait()
-            .unwrap();
-
-        let mut gs = GameState {
+    pub fn new(geo: Geography, items: ItemCatalogue, seed: u64) -> GameState {
+        let (market_sender, market_receiver) = channel();
+        GameState {
             world: World {
                 geography: geo,
+                items: items,
                 time: Time::new(),
-                humans: Vec::new(),
-                containers: Vec::new(),
+                humans: Arena::new(),
+                households: Arena::new(),
+                containers: Arena::new(),
                 weather: Weather::new(),
-                crops: Vec::new(),
-                inventories: Vec::new(),
+                crops: Arena::new(),
+                inventories: Arena::new(),
+                events: Vec::new(),
+                shrines: Vec::new(),
+                markets: Vec::new(),
+                workshops: Vec::new(),
+                wells: Vec::new(),
+                beds: Vec::new(),
             },
-            minds: Vec::new(),
-            inventory_senders: Vec::new(),
-            inventory_receivers: Vec::new(),
-        };
+            minds: HandleMap::new(),
+            seed: seed,
+            names: DEFAULT_NAMES.iter().map(|name| name.to_string()).collect(),
+            divine_power: DivinePower::new(0.0),
+            inventory_senders: HandleMap::new(),
+            inventory_receivers: HandleMap::new(),
+            market_sender: market_sender,
+            market_receiver: market_receiver,
+        }
+    }
 
-        let mut crop = Crop::new(Vector::new(29.5, 16.5), gs.create_inventory(10.0));
-        // TODO remove, just testing storage by making sure crop has grown some food
-        gs.world.inventories[crop.inventory_id].do_give_up_to(Item::Food, 10);
+    /// `seed` overrides the scenario's own seed when given.
+    pub fn from_scenario(
+        geo: Geography,
+        items: ItemCatalogue,
+        scenario: Scenario,
+        seed: Option<u64>,
+    ) -> GameState {
+        let mut gs = GameState::new(geo, items, seed.or(scenario.seed).unwrap_or(0));
+        gs.world.time = scenario.start;
+        gs.names = scenario.names;
+        gs.divine_power = DivinePower::new(scenario.power);
+        for location in scenario.shrines {
+            gs.world.shrines.push(Shrine { location: location });
+        }
+        for location in scenario.wells {
+            gs.world.wells.push(Well { location: location });
+        }
+        for spec in scenario.beds {
+            gs.world.beds.push(Bed {
+                location: spec.location,
+                capacity: spec.capacity,
+            });
+        }
+        for spec in scenario.markets {
+            let stall_inventory_ids = (0..spec.stalls)
+                .map(|_| gs.create_inventory(STALL_CAPACITY))
+                .collect();
+            gs.world
+                .markets
+                .push(Market::new(spec.location, stall_inventory_ids));
+        }
 
-        gs.world.crops.push(crop);
+        let mut container_ids: Vec<ContainerId> = Vec::new();
+        for spec in scenario.containers {
+            let container = Container {
+                location: spec.location,
+                inventory_id: gs.create_inventory(spec.capacity),
+            };
+            gs.world.inventories[container.inventory_id].set_spoilage(spec.spoilage);
+            for (item, count) in spec.items {
+                gs.world.inventories[container.inventory_id].do_give_up_to(
+                    item,
+                    count,
+                    &gs.world.items,
+                );
+            }
+            container_ids.push(gs.world.containers.insert(container));
+        }
 
-        let mut human = Human::new(
-            Vector::new(25.5, 15.0),
-            gs.create_inventory(100.0),
-            Job::Farmer(0),
-        );
-        let mind = Mind::new(Vector::new(29.5, 14.5));
+        let mut crop_ids = Vec::new();
+        for spec in scenario.crops {
+            let crop = Crop::new(spec.location, gs.create_inventory(spec.capacity));
+            // nothing goes off in the field, so what's picked comes in fresh
+            gs.world.inventories[crop.inventory_id].set_spoilage(0.0);
+            for (item, count) in spec.items {
+                gs.world.inventories[crop.inventory_id].do_give_up_to(item, count, &gs.world.items);
+            }
+            crop_ids.push(gs.world.crops.insert(crop));
+        }
 
-        let mut food_box = Container {
-            location: Vector::new(29.5, 14.5),
-            inventory_id: gs.create_inventory(10e10),
-        };
-        gs.world.inventories[food_box.inventory_id].do_give_up_to(Item::Food, 100);
-        human.give_container(0);
+        for spec in scenario.workshops {
+            let workshop = Workshop {
+                location: spec.location,
+                building: spec.building,
+                inventory_id: gs.create_inventory(spec.capacity),
+            };
+            for (item, count) in spec.items {
+                gs.world.inventories[workshop.inventory_id].do_give_up_to(
+                    item,
+                    count,
+                    &gs.world.items,
+                );
+            }
+            gs.world.workshops.push(workshop);
+        }
 
-        gs.world.humans.push(human);
-        gs.minds.push(mind);
-        gs.world.containers.push(food_box);
+        let household_ids: Vec<_> = scenario
+            .households
+            .into_iter()
+            .map(|spec| {
+                let mut household = Household::new(spec.home);
+                for container_index in spec.owned_containers {
+                    household.add_container(container_ids[container_index]);
+                }
+                gs.world.households.insert(household)
+            })
+            .collect();
+
+        let mut name_rng = stream_rng(gs.seed, NAME_STREAM, gs.world.time.ticks_elapsed());
+        let mut personality_rng =
+            stream_rng(gs.seed, PERSONALITY_STREAM, gs.world.time.ticks_elapsed());
+        let today = gs.world.time.today();
+        for spec in scenario.humans {
+            let household_id = household_ids[spec.household];
+            let name = spec.name.unwrap_or_else(|| gs.pick_name(&mut name_rng));
+            let mut human = Human::new(
+                name,
+                spec.location,
+                gs.create_inventory(spec.capacity),
+                household_id,
+                spec.job.job(&crop_ids),
+                Personality::random(&mut personality_rng),
+                spec.age * DAYS_PER_YEAR,
+                today,
+            );
+            let stores = gs.world.households[household_id].containers().len();
+            if stores > 0 {
+                human.note(today, LifeEvent::GainedStores(stores));
+            }
+            for (item, count) in spec.items {
+                gs.world.inventories[human.inventory_id].do_give_up_to(
+                    item,
+                    count,
+                    &gs.world.items,
+                );
+            }
+            let human_id = gs.world.humans.insert(human);
+            gs.world.households[household_id].add_member(human_id);
+            gs.minds.insert(human_id, Mind::new());
+        }
 
         gs
     }
 
     pub fn update(&mut self) {
+        let seed = self.seed;
+        let tick = self.world.time.ticks_elapsed();
+        let today = self.world.time.today();
+        self.bury_dead(tick);
         if self.world.time.is_new_day() {
-            self.world.weather.update();
-            let (sun, rain) = (self.world.weather.sun(), self.world.weather.rain());
             self.world
-                .crops
+                .humans
                 .par_iter_mut()
-                .for_each_with(self.inventory_senders.clone(), |senders, crop| {
-                    crop.grow(sun, rain, senders)
+                .for_each(|(human_id, human)| {
+                    let mut rng = stream_rng(seed, HEALTH_STREAM + human_id.index() as u64, tick);
+                    human.grow_older(&mut rng, today);
+                    human.daily_health(&mut rng, today);
                 });
+            self.start_families(&mut stream_rng(seed, FAMILY_STREAM, tick), tick);
+            self.daily_relationships();
+            let items = &self.world.items;
+            self.world
+                .inventories
+                .par_iter_mut()
+                .for_each(|(_, inventory)| inventory.spoil(items));
+            self.world
+                .weather
+                .update(&mut stream_rng(seed, WEATHER_STREAM, tick));
+            let (sun, rain) = (self.world.weather.sun(), self.world.weather.rain());
+            if rain > 0 {
+                self.world
+                    .events
+                    .push(EventRecord::new(tick, None, WorldEvent::Rain(rain)));
+            }
+            let inventories = &self.world.inventories;
+            let mut harvest_events: Vec<EventRecord> = self
+                .world
+                .crops
+                .par_iter_mut()
+                .map_with(
+                    self.inventory_senders.clone(),
+                    |senders, (crop_id, crop)| {
+                        let water = inventories[crop.inventory_id].count(Item::WATER);
+                        let growth = crop.grow(sun, rain, water, senders);
+                        let event = if growth > 0 {
+                            Some(WorldEvent::GoodHarvest(crop_id, growth))
+                        } else if sun > 0 {
+                            Some(WorldEvent::CropFailed(crop_id))
+                        } else {
+                            None
+                        };
+                        event.map(|event| EventRecord::new(tick, Some(crop.location), event))
+                    },
+                )
+                .filter_map(|event| event)
+                .collect();
+            self.world.events.append(&mut harvest_events);
         }
         {
             let world = &self.world;
-            self.minds
-                .par_iter_mut()
-                .zip(&self.world.humans)
-                .for_each(|(mind, human)| mind.think(&human, world));
+            self.minds.par_iter_mut().for_each(|(human_id, mind)| {
+                let mut rng = stream_rng(seed, MIND_STREAM + human_id.index() as u64, tick);
+                mind.think(human_id, &world.humans[human_id], world, &mut rng)
+            });
         }
 
-        self.minds
-            .par_iter_mut()
-            .zip(self.world.humans.par_iter_mut())
-            .for_each_with(self.inventory_senders.clone(), |senders, (mind, human)| {
-                mind.act(human, senders)
-            });
-        self.inventory_receivers
-            .par_iter_mut()
-            .zip(self.world.inventories.par_iter_mut())
+        let items = &self.world.items;
+        self.world
+            .humans
+            .par_zip_mut(&mut self.minds)
+            .for_each_with(
+                (self.inventory_senders.clone(), self.market_sender.clone()),
+                |(senders, market_sender), (_, human, mind)| {
+                    mind.act(human, items, senders, market_sender)
+                },
+            );
+        let mut market_messages: Vec<MarketMessage> = self.market_receiver.try_iter().collect();
+        market_messages.sort();
+        for msg in market_messages {
+            match msg {
+                MarketMessage::OpenStall(market_index, seller_inventory_id, item, quantity) => {
+                    // if every stall is taken the seller just keeps their goods
+                    self.world.markets[market_index].open_stall(
+                        tick,
+                        seller_inventory_id,
+                        item,
+                        quantity,
+                        &self.inventory_senders,
+                    );
+                }
+                MarketMessage::Bought(market_index, stall_inventory_id, buyer_inventory_id) => {
+                    let seller = self.world.markets[market_index]
+                        .seller(stall_inventory_id)
+                        .and_then(|seller_inventory_id| self.world.carrier(seller_inventory_id));
+                    let buyer = self.world.carrier(buyer_inventory_id);
+                    if let (Some(buyer), Some(seller)) = (buyer, seller) {
+                        if buyer != seller {
+                            self.introduce(buyer, seller, Contact::Trade);
+                        }
+                    }
+                }
+            }
+        }
+        self.meet_neighbours();
+        self.converse(&mut stream_rng(seed, SOCIAL_STREAM, tick));
+        for market in self.world.markets.iter_mut() {
+            market.update(tick, &self.world.inventories, &self.inventory_senders);
+        }
+        // summed in order, since a parallel float sum isn't reproducible
+        let devotion: f32 = self
+            .minds
+            .iter()
+            .map(|(human_id, mind)| mind.devotion(&self.world.humans[human_id], &self.world))
+            .sum();
+        self.divine_power.update(devotion);
+        // snapshot every mailbox before processing any of them, so replies always land next tick
+        // instead of racing the inventory they're sent to
+        let mut messages: HandleMap<Inventory, Vec<ItemMessage>> = HandleMap::new();
+        for (inventory_id, recv) in self.inventory_receivers.iter() {
+            messages.insert(inventory_id, recv.try_iter().collect());
+        }
+        let items = &self.world.items;
+        self.world
+            .inventories
+            .par_zip_mut(&mut messages)
             .for_each_with(
                 self.inventory_senders.clone(),
-                |senders, (recv, inventory)| inventory.receive_all(recv, senders),
+                |senders, (_, inventory, messages)| inventory.receive_all(messages, items, senders),
             );
+        self.world
+            .events
+            .retain(|record| record.tick + TICKS_PER_DAY > tick);
         self.world.time.tick();
     }
 
     pub fn do_travel(&mut self, updates_per_tick: u8) {
-        self.minds
-            .par_iter_mut()
-            .zip(self.world.humans.par_iter_mut())
-            .for_each(|(mind, human)| mind.travel(human, updates_per_tick));
+        self.world
+            .humans
+            .par_zip_mut(&mut self.minds)
+            .for_each(|(_, human, mind)| mind.travel(human, updates_per_tick));
+    }
+
+    /// Works a miracle on the world, and records it as an event for anyone nearby to notice.
+    pub fn perform_miracle(&mut self, miracle: Miracle) -> std::result::Result<(), MiracleError> {
+        let location = match miracle {
+            Miracle::Rain | Miracle::Sunshine => None,
+            Miracle::BlessCrop(crop_id) => Some(
+                self.world
+                    .crops
+                    .get(crop_id)
+                    .ok_or(MiracleError::InvalidTarget)?
+                    .location,
+            ),
+            Miracle::Conjure(inventory_id, _, _) | Miracle::Destroy(inventory_id, _, _) => Some(
+                self.world
+                    .inventory_location(inventory_id)
+                    .ok_or(MiracleError::InvalidTarget)?,
+            ),
+            Miracle::Heal(human_id) | Miracle::Exhaust(human_id) => Some(
+                self.world
+                    .humans
+                    .get(human_id)
+                    .ok_or(MiracleError::InvalidTarget)?
+                    .location,
+            ),
+        };
+        self.divine_power.spend(miracle.cost())?;
+
+        match miracle {
+            Miracle::Rain => {
+                self.world.weather.force(-MIRACLE_WEATHER);
+                let rain = self.world.weather.rain();
+                for crop in self.world.crops.values() {
+                    self.inventory_senders.send(
+                        crop.inventory_id,
+                        ItemMessage::GiveOrDrop(Item::WATER, rain),
+                    );
+                }
+            }
+            Miracle::Sunshine => self.world.weather.force(MIRACLE_WEATHER),
+            Miracle::BlessCrop(crop_id) => self.world.crops[crop_id].bless(),
+            Miracle::Conjure(inventory_id, item, count) => {
+                self.inventory_senders
+                    .send(inventory_id, ItemMessage::GiveOrDrop(item, count));
+            }
+            Miracle::Destroy(inventory_id, item, count) => {
+                self.inventory_senders
+                    .send(inventory_id, ItemMessage::Remove(item, count));
+            }
+            Miracle::Heal(human_id) => self.world.humans[human_id].heal(),
+            Miracle::Exhaust(human_id) => self.world.humans[human_id].fatigue += EXHAUSTION,
+        }
+
+        let tick = self.world.time.ticks_elapsed();
+        self.world.events.push(EventRecord::new(
+            tick,
+            location,
+            WorldEvent::Miracle(miracle),
+        ));
+        Ok(())
+    }
+
+    pub fn divine_power(&self) -> &DivinePower {
+        &self.divine_power
+    }
+
+    /// For player actions other than miracles that still draw on divine power.
+    pub fn spend_power(&mut self, cost: f32) -> std::result::Result<(), InsufficientPower> {
+        self.divine_power.spend(cost)
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Writes the whole game to a string. Messages still waiting in inventory mailboxes are
+    /// saved too, which means draining and re-queueing them, hence `&mut self`.
+    pub fn save(&mut self) -> String {
+        let mut writer = SaveWriter::new();
+        writer.tag(SAVE_MAGIC);
+        writer.put(SAVE_VERSION);
+        writer.tag("seed");
+        writer.put(self.seed);
+        writer.tag("names");
+        writer.put(self.names.len());
+        for name in &self.names {
+            writer.put(name);
+        }
+        self.divine_power.save(&mut writer);
+        self.world.save(&mut writer);
+        self.minds.save("minds", &mut writer);
+        for (inventory_id, recv) in self.inventory_receivers.iter() {
+            let pending: Vec<ItemMessage> = recv.try_iter().collect();
+            writer.put_list("mailbox", &pending);
+            for msg in pending {
+                self.inventory_senders.send(inventory_id, msg);
+            }
+        }
+        writer.finish()
+    }
+
+    pub fn load(data: &str) -> std::result::Result<GameState, SaveError> {
+        let mut reader = SaveReader::new(data);
+        reader.expect(SAVE_MAGIC)?;
+        let version: u32 = reader.get()?;
+        if version != SAVE_VERSION {
+            return Err(SaveError::Version(version));
+        }
+        let seed = reader.get_tagged("seed")?;
+        reader.expect("names")?;
+        let len: usize = reader.get()?;
+        let mut names = Vec::new();
+        for _ in 0..len {
+            names.push(reader.get()?);
+        }
+        let divine_power = DivinePower::load(&mut reader)?;
+        let world = World::load(&mut reader)?;
+        let minds: HandleMap<Human, Mind> = HandleMap::load("minds", &mut reader)?;
+        if minds.len() != world.humans.len() {
+            return Err(SaveError::Format(format!(
+                "{} minds for {} humans",
+                minds.len(),
+                world.humans.len()
+            )));
+        }
+        if let Some(human_id) = world.humans.handles().find(|&id| minds.get(id).is_none()) {
+            return Err(SaveError::Format(format!("human {} has no mind", human_id)));
+        }
+        world.check_references()?;
+        for mind in minds.values() {
+            mind.check_references(&world)?;
+        }
+
+        let (market_sender, market_receiver) = channel();
+        let mut gs = GameState {
+            world: world,
+            minds: minds,
+            seed: seed,
+            names: names,
+            divine_power: divine_power,
+            inventory_senders: HandleMap::new(),
+            inventory_receivers: HandleMap::new(),
+            market_sender: market_sender,
+            market_receiver: market_receiver,
+        };
+        // channels can't be saved, so every inventory gets a fresh one with its old mail re-sent
+        let inventory_ids: Vec<InventoryId> = gs.world.inventories.handles().collect();
+        for inventory_id in inventory_ids {
+            let (send, recv) = channel();
+            let pending: Vec<ItemMessage> = reader.get_list("mailbox")?;
+            for msg in pending {
+                let _ = send.send(msg);
+            }
+            gs.inventory_senders.insert(inventory_id, send);
+            gs.inventory_receivers.insert(inventory_id, recv);
+        }
+        Ok(gs)
     }
 
-    fn create_inventory(&mut self, capacity: f32) -> usize {
-        let index = self.world.inventories.len();
+    pub fn save_to_path<P: AsRef<Path>>(&mut self, path: P) -> std::result::Result<(), SaveError> {
+        fs::write(path, self.save())?;
+        Ok(())
+    }
+
+    pub fn load_from_path<P: AsRef<Path>>(path: P) -> std::result::Result<GameState, SaveError> {
+        GameState::load(&fs::read_to_string(path)?)
+    }
+
+    /// Once a day: single adults who meet settle down together, in the home of whoever asked,
+    /// and couples with food to spare have children.
+    fn start_families<R: Rng>(&mut self, rng: &mut R, tick: u64) {
+        let today = self.world.time.today();
+        let human_ids: Vec<HumanId> = self.world.humans.handles().collect();
+        for human_id in human_ids {
+            if !self.world.humans[human_id].can_partner() || !rng.gen_bool(PARTNERING_CHANCE) {
+                continue;
+            }
+            let location = self.world.humans[human_id].location;
+            let fondness = self.world.humans[human_id].relationships();
+            let other = self
+                .world
+                .humans
+                .iter()
+                .filter(|&(other, human)| {
+                    other != human_id
+                        && human.can_partner()
+                        && human.location.distance(location) <= MEETING_DISTANCE
+                })
+                // whoever they're fondest of, or failing that the nearest
+                .min_by(|&(a_id, a), &(b_id, b)| {
+                    fondness
+                        .affection(b_id)
+                        .partial_cmp(&fondness.affection(a_id))
+                        .unwrap_or(std::cmp::Ordering::Equal)
+                        .then_with(|| {
+                            a.location
+                                .distance(location)
+                                .partial_cmp(&b.location.distance(location))
+                                .unwrap_or(std::cmp::Ordering::Equal)
+                        })
+                })
+                .map(|(other, _)| other);
+            if let Some(other) = other {
+                self.world.humans[human_id].set_partner(Some(other));
+                self.world.humans[other].set_partner(Some(human_id));
+                let name = self.world.humans[human_id].name().to_string();
+                let other_name = self.world.humans[other].name().to_string();
+                self.world.humans[human_id].note(today, LifeEvent::Partnered(other_name));
+                self.world.humans[other].note(today, LifeEvent::Partnered(name));
+                let household_id = self.world.humans[human_id].household();
+                self.move_household(other, household_id);
+            }
+        }
+
+        let mut births = Vec::new();
+        for (human_id, human) in self.world.humans.iter() {
+            if let Some(partner) = human.partner() {
+                // each couple once
+                if human_id < partner
+                    && human.is_fertile()
+                    && self.world.humans[partner].is_fertile()
+                    && human.owned_item_count(Item::FOOD, &self.world) >= CHILD_FOOD
+                    && rng.gen_bool(BIRTH_CHANCE)
+                {
+                    births.push(human_id);
+                }
+            }
+        }
+        let mut name_rng = stream_rng(self.seed, NAME_STREAM, tick);
+        let mut personality_rng = stream_rng(self.seed, PERSONALITY_STREAM, tick);
+        for human_id in births {
+            let inventory_id = self.create_inventory(CHILD_CAPACITY);
+            let name = self.pick_name(&mut name_rng);
+            for parent in self.world.humans[human_id]
+                .partner()
+                .into_iter()
+                .chain(Some(human_id))
+            {
+                self.world.humans[parent].note(today, LifeEvent::HadChild(name.clone()));
+            }
+            let personality = Personality::random(&mut personality_rng);
+            let mut child =
+                self.world.humans[human_id].child(inventory_id, name, personality, today);
+            let home = child.home(&self.world);
+            child.location = home;
+            self.world
+                .events
+                .push(EventRecord::new(tick, Some(home), WorldEvent::Birth));
+            let child_id = self.world.humans.insert(child);
+            self.world.households[self.world.humans[child_id].household()].add_member(child_id);
+            self.minds.insert(child_id, Mind::new());
+        }
+    }
+
+    /// Removes whoever died last tick, leaving word of it for anyone nearby to notice. What they
+    /// carried is buried with them; what they owned stays where it is.
+    fn bury_dead(&mut self, tick: u64) {
+        let dead: Vec<HumanId> = self
+            .world
+            .humans
+            .iter()
+            .filter(|(_, human)| human.is_dead())
+            .map(|(human_id, _)| human_id)
+            .collect();
+        for human_id in dead {
+            let human = match self.world.humans.remove(human_id) {
+                Some(human) => human,
+                None => continue,
+            };
+            self.minds.remove(human_id);
+            for (_, other) in self.world.humans.iter_mut() {
+                other.relationships_mut().forget(human_id);
+            }
+            self.remove_inventory(human.inventory_id);
+            self.leave_household(human_id, human.household());
+            if let Some(partner) = human.partner() {
+                if let Some(widowed) = self.world.humans.get_mut(partner) {
+                    widowed.set_partner(None);
+                    widowed.note(
+                        self.world.time.today(),
+                        LifeEvent::Widowed(human.name().to_string()),
+                    );
+                }
+            }
+            self.world.events.push(EventRecord::new(
+                tick,
+                Some(human.location),
+                WorldEvent::Death(human.cause_of_death()),
+            ));
+        }
+    }
+
+    /// Everyone standing on the same tile as someone else gets to know them a little better.
+    fn meet_neighbours(&mut self) {
+        let mut tiles: BTreeMap<TilePoint, Vec<HumanId>> = BTreeMap::new();
+        for (human_id, human) in self.world.humans.iter() {
+            tiles
+                .entry(TilePoint::from_vector(&human.location))
+                .or_insert(Vec::new())
+                .push(human_id);
+        }
+        for (_, human_ids) in tiles {
+            for &human_id in &human_ids {
+                for &other in &human_ids {
+                    if other != human_id {
+                        self.world.humans[human_id]
+                            .relationships_mut()
+                            .meet(other, Contact::Nearby);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Now and then, humans awake on the same or neighbouring tiles stop for a chat, telling each
+    /// other what they know and think. The more sociable either of them is, the likelier, and
+    /// likelier still if one has gone out of their way to spend time with friends.
+    fn converse<R: Rng>(&mut self, rng: &mut R) {
+        let human_ids: Vec<HumanId> = self.world.humans.handles().collect();
+        for (i, &human_id) in human_ids.iter().enumerate() {
+            for &other in &human_ids[i + 1..] {
+                let tile = TilePoint::from_vector(&self.world.humans[human_id].location);
+                let other_tile = TilePoint::from_vector(&self.world.humans[other].location);
+                let sociability = self.world.humans[human_id].personality().sociability
+                    + self.world.humans[other].personality().sociability;
+                let chatter = if self.minds[human_id].is_socialising()
+                    || self.minds[other].is_socialising()
+                {
+                    VISITING_CHATTER
+                } else {
+                    1.0
+                };
+                if !tile.is_adjacent(&other_tile)
+                    || !self.minds[human_id].can_talk()
+                    || !self.minds[other].can_talk()
+                    || !rng.gen_bool(CONVERSATION_CHANCE * sociability as f64 * chatter)
+                {
+                    continue;
+                }
+                let said = self.minds[human_id].gossip();
+                let replied = self.minds[other].gossip();
+                self.minds[other].hear(&self.world.humans[other], human_id, &said);
+                self.minds[human_id].hear(&self.world.humans[human_id], other, &replied);
+                let clash = self.world.humans[human_id]
+                    .personality()
+                    .clash(self.world.humans[other].personality())
+                    .max(said.disagreement(&replied) / 2.0);
+                let contact = if rng.gen_bool(quarrel_chance(clash) as f64) {
+                    Contact::Argument
+                } else {
+                    Contact::Talk
+                };
+                self.introduce(human_id, other, contact);
+            }
+        }
+    }
+
+    /// A day's worth of living and working together, and of drifting apart from everyone else.
+    fn daily_relationships(&mut self) {
+        let mut contacts = Vec::new();
+        for (human_id, human) in self.world.humans.iter() {
+            for (other, other_human) in self.world.humans.iter() {
+                if other <= human_id {
+                    continue;
+                }
+                if human.household() == other_human.household() {
+                    contacts.push((human_id, other, Contact::Household));
+                }
+                if human.stage() != LifeStage::Child
+                    && other_human.stage() != LifeStage::Child
+                    && human.job().works_with(other_human.job())
+                {
+                    contacts.push((human_id, other, Contact::Colleague));
+                }
+            }
+        }
+        for (_, human) in self.world.humans.iter_mut() {
+            human.relationships_mut().fade();
+        }
+        for (human_id, other, contact) in contacts {
+            self.introduce(human_id, other, contact);
+        }
+    }
+
+    /// Brings two humans into contact, both ways.
+    fn introduce(&mut self, human_id: HumanId, other: HumanId, contact: Contact) {
+        self.world.humans[human_id]
+            .relationships_mut()
+            .meet(other, contact);
+        self.world.humans[other]
+            .relationships_mut()
+            .meet(human_id, contact);
+    }
+
+    /// Moves someone in with another household, bringing along whatever their old one owned if
+    /// they were the last to leave it.
+    fn move_household(&mut self, human_id: HumanId, household_id: HouseholdId) {
+        let old_household_id = self.world.humans[human_id].household();
+        if old_household_id == household_id {
+            return;
+        }
+        if let Some(containers) = self.leave_household(human_id, old_household_id) {
+            if !containers.is_empty() {
+                let today = self.world.time.today();
+                for &member in self.world.households[household_id].members() {
+                    self.world.humans[member]
+                        .note(today, LifeEvent::GainedStores(containers.len()));
+                }
+            }
+            for container_id in containers {
+                self.world.households[household_id].add_container(container_id);
+            }
+        }
+        self.world.humans[human_id].set_household(household_id);
+        self.world.households[household_id].add_member(human_id);
+    }
+
+    /// Takes someone off a household's members. A household left empty is no more, and its
+    /// containers are returned.
+    fn leave_household(
+        &mut self,
+        human_id: HumanId,
+        household_id: HouseholdId,
+    ) -> Option<Vec<ContainerId>> {
+        let household = self.world.households.get_mut(household_id)?;
+        household.remove_member(human_id);
+        if household.members().is_empty() {
+            self.world
+                .households
+                .remove(household_id)
+                .map(|household| household.containers().clone())
+        } else {
+            None
+        }
+    }
+
+    /// A name from the list, preferring one nobody living already goes by.
+    fn pick_name<R: Rng>(&self, rng: &mut R) -> String {
+        let unused: Vec<&String> = self
+            .names
+            .iter()
+            .filter(|&name| self.world.humans.values().all(|human| human.name() != name))
+            .collect();
+        if unused.is_empty() {
+            self.names[rng.gen_range(0, self.names.len())].clone()
+        } else {
+            unused[rng.gen_range(0, unused.len())].clone()
+        }
+    }
+
+    fn create_inventory(&mut self, capacity: f32) -> InventoryId {
+        let inventory_id = self.world.inventories.insert(Inventory::new(capacity));
         let (send, recv) = channel();
-        self.world.inventories.push(Inventory::new(capacity));
-        self.inventory_senders.push(send);
-        self.inventory_receivers.push(recv);
-        index
+        self.inventory_senders.insert(inventory_id, send);
+        self.inventory_receivers.insert(inventory_id, recv);
+        inventory_id
+    }
+
+    /// Gets rid of an inventory and its mailbox. Mail already on its way there, or sent there
+    /// later by anyone still holding its id, is dropped.
+    fn remove_inventory(&mut self, inventory_id: InventoryId) -> Option<Inventory> {
+        self.inventory_senders.remove(inventory_id);
+        self.inventory_receivers.remove(inventory_id);
+        self.world.inventories.remove(inventory_id)
+    }
+}
+
+// the chance that a conversation between two people who clash this much turns into a row
+fn quarrel_chance(clash: f32) -> f32 {
+    ((clash - TOLERANCE) * QUARRELSOMENESS).max(0.0).min(1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::Simulation;
+
+    const VILLAGE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/static/data/village.scenario");
+
+    fn village(ticks: u64) -> GameState {
+        let mut sim = Simulation::from_scenario_path(VILLAGE, Some(3)).unwrap();
+        sim.run(ticks);
+        sim.game_state
+    }
+
+    fn load_error(data: &str) -> SaveError {
+        match GameState::load(data) {
+            Ok(_) => panic!("save should have been rejected"),
+            Err(err) => err,
+        }
+    }
+
+    #[test]
+    fn loaded_village_carries_on_the_same() {
+        let mut saved = Simulation::new(village(500));
+        let mut loaded = Simulation::new(GameState::load(&saved.game_state.save()).unwrap());
+        assert_eq!(
+            saved.game_state.world.fingerprint(),
+            loaded.game_state.world.fingerprint()
+        );
+        for _ in 0..10 {
+            saved.run(50);
+            loaded.run(50);
+            assert_eq!(
+                saved.game_state.world.fingerprint(),
+                loaded.game_state.world.fingerprint()
+            );
+        }
+    }
+
+    #[test]
+    fn truncated_save_rejected() {
+        let data = village(100).save();
+        match load_error(&data[..data.len() / 2]) {
+            SaveError::Format(_) => (),
+            err => panic!("wrong error: {}", err),
+        }
+    }
+
+    #[test]
+    fn dangling_handle_rejected() {
+        let mut gs = village(100);
+        let inventory_id = gs.world.containers.values().next().unwrap().inventory_id;
+        gs.remove_inventory(inventory_id);
+        match load_error(&gs.save()) {
+            SaveError::Format(message) => assert!(message.contains(&inventory_id.to_string())),
+            err => panic!("wrong error: {}", err),
+        }
+    }
+
+    #[test]
+    fn quarrels_start_past_tolerance() {
+        assert_eq!(quarrel_chance(0.0), 0.0);
+        assert_eq!(quarrel_chance(TOLERANCE), 0.0);
+        assert!(quarrel_chance(TOLERANCE + 0.25) > quarrel_chance(TOLERANCE + 0.125));
+        assert_eq!(quarrel_chance(TOLERANCE + 0.5), 0.5 * QUARRELSOMENESS);
+        assert_eq!(quarrel_chance(10.0), 1.0);
     }
 }
@@ -2,17 +2,39 @@ use num_traits::ops::saturating::Saturating;
 use quicksilver::geom::Vector;
 use std::cmp::Ordering;
 use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::error;
+use std::fmt;
 use std::u32;
 
+use crate::save::{Save, SaveError, SaveReader, SaveWriter};
+
+pub const MAP_WIDTH: usize = 40;
+pub const MAP_HEIGHT: usize = 30;
+
 pub struct Geography {
     pub tiles: Vec<Vec<Tile>>,
     pub width: usize,
     pub height: usize,
+    water_tiles: Vec<TilePoint>, // found once, since the map never changes
+}
+
+#[derive(Debug)]
+pub struct MapError {
+    pub message: String,
 }
 
+impl fmt::Display for MapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl error::Error for MapError {}
+
 pub struct Tile {
     pub terrain_cost: u16,
     pub walls: [bool; 4], // css/clockwise order: top, right, bottom, left
+    pub water: bool,      // a river or pond, which anyone can drink from
 }
 
 impl Tile {
@@ -25,7 +47,7 @@ impl Tile {
     }
 }
 
-#[derive(Copy, Clone, Eq, PartialEq, Hash)]
+#[derive(Copy, Clone, Eq, PartialEq, Hash, PartialOrd, Ord)]
 pub struct TilePoint {
     pub x: usize,
     pub y: usize,
@@ -54,16 +76,40 @@ impl PartialOrd for PathState {
 }
 
 impl Geography {
-    pub fn from_data(width: usize, height: usize, data: &[u8]) -> Geography {
+    /// Reads a map drawn in text, with a character for every tile and every wall between tiles.
+    /// Fails if the map has no tiles or `data` is too short to hold them all.
+    pub fn from_data(width: usize, height: usize, data: &[u8]) -> Result<Geography, MapError> {
+        if width == 0 || height == 0 {
+            return Err(MapError {
+                message: format!("a {}x{} map has no tiles", width, height),
+            });
+        }
+        // down to the middle of the bottom wall, whether or not the file ends in a newline
+        if width > data.len()
+            || height > data.len()
+            || data.len() < (width * 2 + 2) * height * 2 + width * 2
+        {
+            return Err(MapError {
+                message: format!(
+                    "{} bytes is too short for a {}x{} map",
+                    data.len(),
+                    width,
+                    height
+                ),
+            });
+        }
+
         let mut tiles = Vec::new();
         for x in 0..width {
             let mut col = Vec::new();
             for y in 0..height {
+                // width + 1 everywhere to account for newlines
+                let terrain = data[(width * 2 + 2) * (2 * y + 1) + (x * 2 + 1)] as char;
                 col.push(Tile {
-                    // width + 1 everywhere to account for newlines
-                    terrain_cost: match data[(width * 2 + 2) * (2 * y + 1) + (x * 2 + 1)] as char {
-                        '+' => 1u16, // road
-                        _ => 5u16,   // anything else
+                    terrain_cost: match terrain {
+                        '+' => 1u16,  // road
+                        '~' => 20u16, // water, to be waded through
+                        _ => 5u16,    // anything else
                     },
                     walls: [
                         data[(width * 2 + 2) * 2 * y + (x * 2 + 1)] == '-' as u8,
@@ -71,18 +117,37 @@ impl Geography {
                         data[(width * 2 + 2) * (2 * y + 2) + (x * 2 + 1)] == '-' as u8,
                         data[(width * 2 + 2) * (2 * y + 1) + x * 2] == '|' as u8,
                     ],
+                    water: terrain == '~',
                 })
             }
             tiles.push(col);
         }
 
+        Ok(Geography::new(tiles, width, height))
+    }
+
+    fn new(tiles: Vec<Vec<Tile>>, width: usize, height: usize) -> Geography {
+        let mut water_tiles = Vec::new();
+        for (x, col) in tiles.iter().enumerate() {
+            for (y, tile) in col.iter().enumerate() {
+                if tile.water {
+                    water_tiles.push(TilePoint::new(x, y));
+                }
+            }
+        }
         Geography {
             tiles: tiles,
             width: width,
             height: height,
+            water_tiles: water_tiles,
         }
     }
 
+    /// Every tile with water on it, column by column.
+    pub fn water_tiles(&self) -> &[TilePoint] {
+        &self.water_tiles
+    }
+
     pub fn find_path(&self, start: TilePoint, goal: TilePoint) -> Option<Vec<TilePoint>> {
         let mut closed_set = HashSet::new();
 
@@ -193,4 +258,82 @@ impl TilePoint {
             y: v.y.floor() as usize,
         }
     }
+
+    /// Whether `other` is this tile or one of the eight around it.
+    pub fn is_adjacent(&self, other: &TilePoint) -> bool {
+        (self.x as isize - other.x as isize).abs() <= 1
+            && (self.y as isize - other.y as isize).abs() <= 1
+    }
+}
+
+impl Save for TilePoint {
+    fn save(&self, writer: &mut SaveWriter) {
+        writer.put(self.x);
+        writer.put(self.y);
+    }
+
+    fn load(reader: &mut SaveReader) -> Result<TilePoint, SaveError> {
+        Ok(TilePoint::new(reader.get()?, reader.get()?))
+    }
+}
+
+impl Save for Geography {
+    fn save(&self, writer: &mut SaveWriter) {
+        writer.tag("geography");
+        writer.put(self.width);
+        writer.put(self.height);
+        for col in &self.tiles {
+            for tile in col {
+                writer.tag("tile");
+                writer.put(tile.terrain_cost);
+                for &wall in &tile.walls {
+                    writer.put(wall as u8);
+                }
+                writer.put(tile.water as u8);
+            }
+        }
+    }
+
+    fn load(reader: &mut SaveReader) -> Result<Geography, SaveError> {
+        reader.expect("geography")?;
+        let width: usize = reader.get()?;
+        let height: usize = reader.get()?;
+        let mut tiles = Vec::new();
+        for _ in 0..width {
+            let mut col = Vec::new();
+            for _ in 0..height {
+                reader.expect("tile")?;
+                let terrain_cost = reader.get()?;
+                let mut walls = [false; 4];
+                for wall in walls.iter_mut() {
+                    *wall = reader.get::<u8>()? != 0;
+                }
+                col.push(Tile {
+                    terrain_cost: terrain_cost,
+                    walls: walls,
+                    water: reader.get::<u8>()? != 0,
+                });
+            }
+            tiles.push(col);
+        }
+        Ok(Geography::new(tiles, width, height))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn map_must_fit_its_data() {
+        let data = include_bytes!("../static/data/test.map");
+        let geography = Geography::from_data(MAP_WIDTH, MAP_HEIGHT, data).unwrap();
+        assert_eq!(geography.tiles.len(), MAP_WIDTH);
+        assert_eq!(geography.tiles[0].len(), MAP_HEIGHT);
+        assert!(Geography::from_data(MAP_WIDTH + 1, MAP_HEIGHT, data).is_err());
+        assert!(Geography::from_data(MAP_WIDTH, MAP_HEIGHT + 1, data).is_err());
+        assert!(Geography::from_data(0, MAP_HEIGHT, data).is_err());
+        assert!(Geography::from_data(MAP_WIDTH, 0, data).is_err());
+        assert!(Geography::from_data(MAP_WIDTH, MAP_HEIGHT, &data[..data.len() / 2]).is_err());
+    }
 }
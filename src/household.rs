@@ -0,0 +1,102 @@
+use std::hash::{Hash, Hasher};
+
+use quicksilver::geom::Vector;
+
+use crate::entity::Handle;
+use crate::human::HumanId;
+use crate::item::Item;
+use crate::save::{Save, SaveError, SaveReader, SaveWriter};
+use crate::world::{ContainerId, World};
+
+pub type HouseholdId = Handle<Household>;
+
+/// People living under one roof. They sleep at the same home, and everything in the household's
+/// containers, money included, is theirs to share: whoever is hungry eats from the same stores
+/// everyone else fills.
+pub struct Household {
+    pub home: Vector,
+    containers: Vec<ContainerId>, // no duplicates
+    members: Vec<HumanId>,
+}
+
+impl Household {
+    pub fn new(home: Vector) -> Household {
+        Household {
+            home: home,
+            containers: Vec::new(),
+            members: Vec::new(),
+        }
+    }
+
+    pub fn containers(&self) -> &Vec<ContainerId> {
+        &self.containers
+    }
+
+    pub fn add_container(&mut self, container_id: ContainerId) {
+        if !self.containers.contains(&container_id) {
+            self.containers.push(container_id);
+        }
+    }
+
+    pub fn members(&self) -> &Vec<HumanId> {
+        &self.members
+    }
+
+    pub fn add_member(&mut self, human_id: HumanId) {
+        if !self.members.contains(&human_id) {
+            self.members.push(human_id);
+        }
+    }
+
+    pub fn remove_member(&mut self, human_id: HumanId) {
+        self.members.retain(|&member| member != human_id);
+    }
+
+    /// How much of `item` is kept in the household's containers, not counting what members
+    /// carry about with them.
+    pub fn stock(&self, item: Item, world: &World) -> u32 {
+        self.containers
+            .iter()
+            .filter_map(|&id| world.containers.get(id))
+            .map(|container| world.inventories[container.inventory_id].count(item))
+            .sum()
+    }
+}
+
+impl Hash for Household {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.home.x.to_bits().hash(state);
+        self.home.y.to_bits().hash(state);
+        self.containers.hash(state);
+        self.members.hash(state);
+    }
+}
+
+impl Save for Household {
+    fn save(&self, writer: &mut SaveWriter) {
+        writer.tag("household");
+        writer.put_vector(self.home);
+        writer.put(self.containers.len());
+        for container_id in &self.containers {
+            writer.put(container_id);
+        }
+        writer.put(self.members.len());
+        for human_id in &self.members {
+            writer.put(human_id);
+        }
+    }
+
+    fn load(reader: &mut SaveReader) -> Result<Household, SaveError> {
+        reader.expect("household")?;
+        let mut household = Household::new(reader.get_vector()?);
+        let len: usize = reader.get()?;
+        for _ in 0..len {
+            household.containers.push(reader.get()?);
+        }
+        let len: usize = reader.get()?;
+        for _ in 0..len {
+            household.members.push(reader.get()?);
+        }
+        Ok(household)
+    }
+}
@@ -1,9 +1,29 @@
+use crate::belief::{Belief, Beliefs};
+use crate::biography::{Biography, LifeEvent};
+use crate::catalogue::ItemCatalogue;
+use crate::entity::Handle;
+use crate::event::{EventRecord, WorldEvent};
 use crate::geography::{Geography, TilePoint};
-use crate::item::{Inventory, Item, ItemMessage};
-use crate::plant::Crop;
-use crate::world::{Container, World, TICKS_PER_MINUTE};
+use crate::household::HouseholdId;
+use crate::item::{Inventory, InventoryId, Item, ItemMessage, Mail, Senders};
+use crate::job::Job;
+use crate::knowledge::{Gossip, Knowledge, StoreReport};
+use crate::market::{self, Market, MarketMessage};
+use crate::miracle::Miracle;
+use crate::personality::Personality;
+use crate::plan::{self, Action, Fact, Place, Step};
+use crate::plant::{Crop, CropId};
+use crate::relationship::Relationships;
+use crate::save::{Save, SaveError, SaveReader, SaveWriter};
+use crate::value;
+use crate::world::{Container, Day, World, TICKS_PER_DAY, TICKS_PER_MINUTE};
 
 use std::cmp::Ordering;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::mem;
+use std::str::FromStr;
 use std::sync::mpsc::Sender;
 
 use quicksilver::geom::Vector;
@@ -30,11 +50,99 @@ const SLEEP_PER_TICK: f32 =
         // day / units sleep
     ) + FATIGUE_PER_TICK; // make up for fatigue added even while sleeping
 
+const WORK_TICKS: u32 = TICKS_PER_MINUTE as u32 * 60 * 6; // work 6 hours / day
+const PRAYER_TICKS: u32 = TICKS_PER_MINUTE as u32 * 30;
+const PRAYER_PIETY: f32 = 0.25; // piety needed before bothering to pray
+const PRAYED_RECENTLY_DAYS: u32 = 3;
+const PERCEPTION_RADIUS: f32 = 10.0;
+const DEVOTION_PER_PRAYER_TICK: f32 = 1.0; // times piety
+const SHRINE_DEVOTION: f32 = 2.0; // prayers at a shrine count this many times over
+const GRATITUDE: f32 = 5.0; // per good harvest, times piety, if fed
+const SATED_HUNGER: f32 = 40.0;
+const THIRST_PER_TICK: f32 = 50.0 / (TICKS_PER_MINUTE as f32 * 60.0 * 24.0);
+const THIRSTY: f32 = 30.0; // thirst at which they drop what they're doing to drink
+const WATER_QUENCH: f32 = 5.0; // thirst quenched per unit of water
+const WATER_DRAW: u32 = 10; // least water drawn at a well or river, to carry some home
+const POCKET_MONEY: u32 = 20; // what they keep on them; the rest goes in the household's stores
+const TRUST_PREMIUM: f32 = 0.2; // extra they'll pay, as a fraction of the price, to a trusted seller
+const HEARSAY_CREDENCE: f32 = 0.25; // a stranger's word next to seeing it; a trusted friend's counts double
+const PERSUASION: f32 = 0.05; // how far one conversation sways an opinion, times credence
+const FRIENDSHIP: f32 = 0.2; // affection at which they'd ask for a meal when they've none of their own
+
+// the trouble of getting a meal one way rather than another, in tiles they'd walk to avoid it
+const EATING_OUT: f32 = 5.0; // rather than at home
+const BUYING_COST: f32 = 10.0; // rather than eat from their own stores
+const NEIGHBOURLY_COST: f32 = 20.0; // rather than buy it
+const MAX_REPLANS: u32 = 3; // times a plan can fall through before they give up on it
+
+// the daily routine, for someone of average temperament; see `Personality`
+const BREAKFAST_HOUR: i32 = 6;
+const WORK_HOUR: i32 = 7;
+const DINNER_HOUR: i32 = 17;
+const PRAYER_HOUR: i32 = 18;
+const MARKET_HOURS: (i32, i32) = (8, 20); // when they'd go shopping for anything but a meal
+const TEMPERAMENT_HOURS: f32 = 2.0; // earliest or latest anyone goes about it, either way
+const BEDTIME_HOUR: i32 = 22;
+const WAKE_HOUR: i32 = 6;
+const DROWSY: f32 = 30.0; // fatigue past which they turn in at night, or lie in come morning
+const NAP_FATIGUE: f32 = 100.0; // fatigue past which they'll sleep in the day
+const DILIGENT_FATIGUE: f32 = 20.0; // more they'll put up with before a nap, at the most diligent
+const DILIGENT_WORK: f32 = 0.25; // more of a day's work they put in, at the most diligent
+const GLUTTONY_APPETITE: f32 = 0.25; // more they eat, at the greediest
+const WANDER_CHANCE: f32 = 0.01; // per tick, twice that at the most curious
+const WANDER_DISTANCE: f64 = 5.0;
+const LEISURE_HOURS: (i32, i32) = (17, 21); // when they'd think of calling on a friend
+const SOCIAL_TICKS: u32 = TICKS_PER_MINUTE as u32 * 60; // time spent in company, once they find it
+
+// how much they'd like to do something, from 0 (not at all) to 1 (nothing else matters)
+const PERSISTENCE: f32 = 0.15; // extra for carrying on with what they're doing, so they don't dither
+const DRINK_UTILITY: f32 = 0.6; // once thirsty, rising to 1 as they're parched
+const MEAL_UTILITY: f32 = 0.55; // once a meal's due, rising to 0.95 as they starve
+const WORK_UTILITY: f32 = 0.45; // a tenth more or less, for the most or least diligent
+const SHOP_UTILITY: f32 = 0.4;
+const SLEEP_UTILITY: f32 = 0.25; // for a nap, rising to 0.95 as they're exhausted
+const NIGHT_SLEEP_UTILITY: f32 = 0.5; // at night, rising the same way
+const PRAYER_UTILITY: f32 = 0.3;
+const SOCIAL_UTILITY: f32 = 0.2; // twice that at the most sociable
+const WANDER_UTILITY: f32 = 0.1;
+
+// how well they rest, as a share of a night's sleep at home
+const BED_QUALITY: f32 = 1.25;
+const FLOOR_QUALITY: f32 = 1.0;
+const ROUGH_QUALITY: f32 = 0.5; // wherever they drop, once too exhausted to get home
+
+pub const MAX_HEALTH: f32 = 100.0;
+// past these, health starts to go; per day
+const STARVING: f32 = 100.0;
+const STARVATION_HARM: f32 = 20.0;
+const PARCHED: f32 = 100.0;
+const THIRST_HARM: f32 = 50.0;
+const EXHAUSTED: f32 = 120.0;
+const EXHAUSTION_HARM: f32 = 20.0;
+const ILLNESS_HARM: f32 = 10.0;
+const RECOVERY: f32 = 10.0; // per day, while nothing else is wrong
+const ILLNESS_CHANCE: f64 = 0.01; // per day
+const ILLNESS_DAYS: (u32, u32) = (2, 7);
+const ACCIDENT_CHANCE: f64 = 0.002; // times the job's hazard
+const ACCIDENT_HARM: (f32, f32) = (10.0, 60.0);
+
+pub const DAYS_PER_YEAR: u32 = 360;
+const ADULT_YEARS: u32 = 16;
+const ELDER_YEARS: u32 = 60;
+const FERTILE_YEARS: u32 = 45; // too old for children after this
+const OLD_AGE_YEARS: f64 = 40.0; // past elder, by when dying of old age gets likely any day
+const OLD_AGE_CHANCE: f64 = 0.01; // per day, once that old
+
 enum Activity {
     Idle,
     Eating(EatingState),
+    Drinking(DrinkingState),
     Sleeping,
     Working(WorkState),
+    Praying,
+    Shopping,
+    Socialising,
+    Wandering(WanderState),
 }
 
 enum EatingState {
@@ -42,22 +150,113 @@ enum EatingState {
     Finding,
 }
 
+enum DrinkingState {
+    Drinking,
+    Finding,
+}
+
 enum WorkState {
     Commuting,
     Working,
     Storing,
+    Selling,
+}
+
+enum WanderState {
+    Starting,
+    Walking,
+}
+
+/// Something a human might take up, and how much they'd like to right now. Every tick they
+/// weigh up all of `CHOICES` and do whatever they'd most like to, so adding an activity means
+/// adding a choice here rather than finding a place for it in a chain of ifs.
+struct Choice {
+    start: fn() -> Activity,
+    utility: fn(&Mind, &Situation) -> f32,
+}
+
+// everything a human might choose to do; ties go to whichever comes first
+const CHOICES: [Choice; 8] = [
+    Choice {
+        start: || Activity::Drinking(DrinkingState::Finding),
+        utility: Mind::drink_utility,
+    },
+    Choice {
+        start: || Activity::Eating(EatingState::Finding),
+        utility: Mind::meal_utility,
+    },
+    Choice {
+        start: || Activity::Working(WorkState::Commuting),
+        utility: Mind::work_utility,
+    },
+    Choice {
+        start: || Activity::Shopping,
+        utility: Mind::shop_utility,
+    },
+    Choice {
+        start: || Activity::Sleeping,
+        utility: Mind::sleep_utility,
+    },
+    Choice {
+        start: || Activity::Praying,
+        utility: Mind::prayer_utility,
+    },
+    Choice {
+        start: || Activity::Socialising,
+        utility: Mind::social_utility,
+    },
+    Choice {
+        start: || Activity::Wandering(WanderState::Starting),
+        utility: Mind::wander_utility,
+    },
+];
+
+// what a human weighs their choices against
+struct Situation<'a> {
+    human: &'a Human,
+    world: &'a World,
+    hour: i32,
+    whim: f32, // a roll of the dice, for whatever they might do on a whim
+}
+
+/// What wore someone's health down.
+#[derive(Copy, Clone, Hash, PartialEq, Debug)]
+pub enum Harm {
+    Starvation,
+    Thirst,
+    Exhaustion,
+    Illness,
+    Injury,
+    OldAge,
 }
 
-pub enum Job {
-    Farmer(usize), // crop id
+/// What someone can do, and how much they eat, depends on how old they are.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum LifeStage {
+    Child,
+    Adult,
+    Elder,
 }
 
+pub type HumanId = Handle<Human>;
+
 pub struct Human {
+    name: String,
     pub location: Vector,
-    pub inventory_id: usize,
+    pub inventory_id: InventoryId,
     pub fatigue: f32,
     pub hunger: f32,
-    owned_container_indeces: Vec<usize>, // should this be a HashSet to handle duplicates? or just one at their home?
+    pub thirst: f32,
+    pub health: f32,
+    illness_days: u32, // still to go; 0 when well
+    last_harm: Option<Harm>,
+    age: u32, // in days
+    born: Day,
+    biography: Biography,
+    partner: Option<HumanId>,
+    household: HouseholdId,
+    relationships: Relationships,
+    personality: Personality,
     speed: f32,
     job: Job,
 }
@@ -66,119 +265,587 @@ pub struct Mind {
     current_path: Vec<TilePoint>,
     state: Activity,
 
-    home: Vector,
-
     had_breakfast: bool,
     had_dinner: bool,
     meal_size: u32,
 
-    target_inventory_id: Option<usize>,
+    target_inventory_id: Option<InventoryId>,
+    plan: Vec<Step>, // what's left of it, next step first
+    replans: u32,    // times the plan has fallen through
 
     progress: u32,
 
     wait: u32,
     travel_vector: Option<Vector>,
+
+    beliefs: Beliefs,
+    knowledge: Knowledge,
+    prayed_today: bool,
+    days_since_prayer: u32,
+    prayer_progress: u32,
+
+    target_market: Option<usize>,
+    sell_quantity: u32,
+    mail: Vec<Mail>, // decided on in think, sent in act
+    sold_today: bool,
+    shopped_today: bool,
+    took_money: bool,         // from the household's stores, for this trip to market
+    went_hungry: Option<Day>, // noticed in think, written into their biography in act
+    socialised_today: bool,
+    company: u32,       // ticks spent with a friend this visit
+    sleep_quality: f32, // how well they're resting where they've lain down; 0 until they have
 }
 
+// a float that can be sorted by, smallest (say, the nearest) first
 #[derive(PartialEq)]
-struct MinFloat(f32);
+struct OrdFloat(f32);
 
-impl Eq for MinFloat {}
+impl Eq for OrdFloat {}
 
-impl PartialOrd for MinFloat {
+impl PartialOrd for OrdFloat {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        other.0.partial_cmp(&self.0)
+        self.0.partial_cmp(&other.0)
     }
 }
 
-impl Ord for MinFloat {
-    fn cmp(&self, other: &MinFloat) -> Ordering {
+impl Ord for OrdFloat {
+    fn cmp(&self, other: &OrdFloat) -> Ordering {
         self.partial_cmp(other).unwrap()
     }
 }
 
+// 0 at `low` or below, rising steadily to 1 at `high` and above
+fn ramp(value: f32, low: f32, high: f32) -> f32 {
+    ((value - low) / (high - low)).max(0.0).min(1.0)
+}
+
 impl Human {
-    pub fn new(location: Vector, inventory_id: usize, job: Job) -> Human {
-        Human {
+    /// `age` is in days, as of `today`.
+    pub fn new(
+        name: String,
+        location: Vector,
+        inventory_id: InventoryId,
+        household: HouseholdId,
+        job: Job,
+        personality: Personality,
+        age: u32,
+        today: Day,
+    ) -> Human {
+        let born = Day(today.0 - age as i64);
+        let mut biography = Biography::new();
+        biography.note(born, LifeEvent::Born);
+        let mut human = Human {
+            name: name,
             location: location,
             inventory_id: inventory_id,
             speed: 0.1,
             fatigue: 80.0,
             hunger: 0.0,
-            owned_container_indeces: Vec::new(),
+            thirst: 0.0,
+            health: MAX_HEALTH,
+            illness_days: 0,
+            last_harm: None,
+            age: age,
+            born: born,
+            biography: biography,
+            partner: None,
+            household: household,
+            relationships: Relationships::new(),
+            personality: personality,
             job: job,
+        };
+        if human.stage() != LifeStage::Child {
+            human.note(today, LifeEvent::TookUpWork(human.job.clone()));
         }
+        human
     }
 
     pub fn description_lines(&self, world: &World) -> Vec<String> {
-        let mut lines = vec!["Human: <name>".to_string()];
+        let mut lines = vec![format!("Human: {}", self.name)];
+        lines.push(format!(
+            "Age: {} ({}), born {}",
+            self.years(),
+            self.stage().name(),
+            self.born.date_string()
+        ));
+        lines.push(format!("Temperament: {}", self.personality.description()));
+        if let Some(partner) = self.partner.and_then(|partner| world.humans.get(partner)) {
+            lines.push(format!("Partner: {}", partner.name));
+        }
+        let household = &world.households[self.household];
+        lines.push(format!(
+            "Household of {} ({} money at home)",
+            household.members().len(),
+            household.stock(Item::MONEY, world)
+        ));
+        lines.push(format!(
+            "Health: {:.2}{}",
+            self.health,
+            if self.is_sick() { " (sick)" } else { "" }
+        ));
         lines.push(format!("Fatigue: {:.2}", self.fatigue));
         lines.push(format!("Hunger: {:.2}", self.hunger));
-        lines.push(format!("Job: {}", match self.job {
-            Job::Farmer(_) => "Farmer",
-        }));
+        lines.push(format!("Thirst: {:.2}", self.thirst));
+        lines.push(format!("Job: {}", self.job.name(world)));
+        lines.push(format!(
+            "Values food at: {:.2}",
+            value::marginal_value(self, Item::FOOD, world)
+        ));
 
-        let mut inventory_lines = world.inventories[self.inventory_id].description_lines();
+        let mut inventory_lines =
+            world.inventories[self.inventory_id].description_lines(&world.items);
         lines.append(&mut inventory_lines);
+        lines.append(&mut self.relationships.description_lines(world));
         lines
     }
 
-    fn owned_item_count(&self, item: Item, world: &World) -> u32 {
-        let container_count: u32 = self
-            .owned_container_indeces
+    /// What they carry, and their household's share of stores.
+    pub fn owned_item_count(&self, item: Item, world: &World) -> u32 {
+        world.inventories[self.inventory_id].count(item)
+            + world.households[self.household].stock(item, world)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn born(&self) -> Day {
+        self.born
+    }
+
+    pub fn biography(&self) -> &Biography {
+        &self.biography
+    }
+
+    pub fn note(&mut self, day: Day, event: LifeEvent) {
+        self.biography.note(day, event);
+    }
+
+    pub fn personality(&self) -> &Personality {
+        &self.personality
+    }
+
+    pub fn relationships(&self) -> &Relationships {
+        &self.relationships
+    }
+
+    pub fn relationships_mut(&mut self) -> &mut Relationships {
+        &mut self.relationships
+    }
+
+    pub fn household(&self) -> HouseholdId {
+        self.household
+    }
+
+    /// Moves them in with another household. Membership is kept by the households themselves.
+    pub fn set_household(&mut self, household: HouseholdId) {
+        self.household = household;
+    }
+
+    pub fn home(&self, world: &World) -> Vector {
+        world.households[self.household].home
+    }
+
+    // the household's containers, nearest first
+    fn containers<'a>(&self, world: &'a World) -> Vec<&'a Container> {
+        let mut containers: Vec<&Container> = world.households[self.household]
+            .containers()
             .iter()
-            .map(|&i| world.inventories[world.containers[i].inventory_id].count(item))
-            .sum();
-        world.inventories[self.inventory_id].count(item) + container_count
+            .filter_map(|&id| world.containers.get(id))
+            .collect();
+        containers.sort_by_key(|container| OrdFloat(self.location.distance(container.location)));
+        containers
+    }
+
+    pub fn is_sick(&self) -> bool {
+        self.illness_days > 0
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.health <= 0.0
     }
 
-    pub fn give_container(&mut self, container_index: usize) {
-        self.owned_container_indeces.push(container_index)
+    /// What did for them, once they're dead.
+    pub fn cause_of_death(&self) -> Harm {
+        self.last_harm.unwrap_or(Harm::Injury)
+    }
+
+    /// Puts everything right, as if they'd never gone without.
+    pub fn heal(&mut self) {
+        self.fatigue = 0.0;
+        self.hunger = 0.0;
+        self.thirst = 0.0;
+        self.health = MAX_HEALTH;
+        self.illness_days = 0;
+    }
+
+    /// Age in whole years.
+    pub fn years(&self) -> u32 {
+        self.age / DAYS_PER_YEAR
+    }
+
+    pub fn stage(&self) -> LifeStage {
+        let years = self.years();
+        if years < ADULT_YEARS {
+            LifeStage::Child
+        } else if years < ELDER_YEARS {
+            LifeStage::Adult
+        } else {
+            LifeStage::Elder
+        }
+    }
+
+    pub fn partner(&self) -> Option<HumanId> {
+        self.partner
+    }
+
+    pub fn set_partner(&mut self, partner: Option<HumanId>) {
+        self.partner = partner;
+    }
+
+    /// Whether they're of an age to settle down, and haven't yet.
+    pub fn can_partner(&self) -> bool {
+        self.partner.is_none() && self.stage() == LifeStage::Adult
+    }
+
+    pub fn is_fertile(&self) -> bool {
+        self.stage() == LifeStage::Adult && self.years() < FERTILE_YEARS
+    }
+
+    /// Once a day. Past a certain age, any day might be the last.
+    pub fn grow_older<R: Rng>(&mut self, rng: &mut R, today: Day) {
+        let stage = self.stage();
+        self.age += 1;
+        if self.stage() != stage {
+            match self.stage() {
+                LifeStage::Adult => {
+                    self.note(today, LifeEvent::CameOfAge);
+                    self.note(today, LifeEvent::TookUpWork(self.job.clone()));
+                }
+                LifeStage::Elder => self.note(today, LifeEvent::GrewOld),
+                LifeStage::Child => (),
+            }
+        }
+        if self.stage() == LifeStage::Elder {
+            let years_past = (self.years() - ELDER_YEARS) as f64;
+            let chance = (years_past / OLD_AGE_YEARS).powi(2) * OLD_AGE_CHANCE;
+            if rng.gen_bool(chance.min(1.0)) {
+                self.harm(Harm::OldAge, self.health);
+            }
+        }
+    }
+
+    /// Once a day: illness runs its course or sets in, and accidents happen.
+    pub fn daily_health<R: Rng>(&mut self, rng: &mut R, today: Day) {
+        if self.illness_days > 0 {
+            self.illness_days -= 1;
+        } else if rng.gen_bool(ILLNESS_CHANCE) {
+            self.illness_days = rng.gen_range(ILLNESS_DAYS.0, ILLNESS_DAYS.1);
+            self.note(today, LifeEvent::FellSick);
+        }
+        if rng.gen_bool(ACCIDENT_CHANCE * self.job.hazard() as f64) {
+            self.harm(
+                Harm::Injury,
+                rng.gen_range(ACCIDENT_HARM.0, ACCIDENT_HARM.1),
+            );
+            self.note(today, LifeEvent::Injured);
+        }
+    }
+
+    fn update_health(&mut self) {
+        let day = TICKS_PER_DAY as f32;
+        let mut well = true;
+        if self.hunger > STARVING {
+            self.harm(Harm::Starvation, STARVATION_HARM / day);
+            well = false;
+        }
+        if self.thirst > PARCHED {
+            self.harm(Harm::Thirst, THIRST_HARM / day);
+            well = false;
+        }
+        if self.fatigue > EXHAUSTED {
+            self.harm(Harm::Exhaustion, EXHAUSTION_HARM / day);
+            well = false;
+        }
+        if self.is_sick() {
+            self.harm(Harm::Illness, ILLNESS_HARM / day);
+            well = false;
+        }
+        if well {
+            self.health = (self.health + RECOVERY / day).min(MAX_HEALTH);
+        }
+    }
+
+    fn harm(&mut self, harm: Harm, amount: f32) {
+        self.health -= amount;
+        self.last_harm = Some(harm);
+    }
+
+    pub fn job(&self) -> &Job {
+        &self.job
+    }
+
+    fn works_crop(&self, crop_id: CropId) -> bool {
+        self.job.works_crop(crop_id)
+    }
+
+    /// Sets up a newborn: the same trade, to take up when grown, and the same household.
+    pub fn child(
+        &self,
+        inventory_id: InventoryId,
+        name: String,
+        personality: Personality,
+        today: Day,
+    ) -> Human {
+        Human::new(
+            name,
+            self.location,
+            inventory_id,
+            self.household,
+            self.job.clone(),
+            personality,
+            0,
+            today,
+        )
+    }
+
+    /// How fast they walk, slower for the young and old.
+    pub fn speed(&self) -> f32 {
+        self.speed * self.stage().speed_share()
     }
 
     fn daily_food(&self, world: &World) -> u32 {
-        let owned_food = self.owned_item_count(Item::Food, world);
-        if self.hunger < 80.0 {
+        // the dearer the next bite is to them, the more they ration
+        let food_value = value::marginal_value(self, Item::FOOD, world);
+        let ration = if self.hunger < 80.0 {
             40
         } else if self.hunger < 110.0 {
-            if owned_food > 80 {
+            if food_value < 2.0 {
                 40
             } else {
                 30
             }
         } else if self.hunger < 130.0 {
-            if owned_food > 60 {
+            if food_value < 2.5 {
                 30
             } else {
                 20
             }
         } else {
-            if owned_food > 40 {
+            if food_value < 3.5 {
                 20
             } else {
                 10
             }
+        };
+        let appetite = 1.0 + GLUTTONY_APPETITE * Personality::lean(self.personality.gluttony);
+        (ration as f32 * self.stage().food_share() * appetite) as u32
+    }
+}
+
+impl Harm {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Harm::Starvation => "starvation",
+            Harm::Thirst => "thirst",
+            Harm::Exhaustion => "exhaustion",
+            Harm::Illness => "illness",
+            Harm::Injury => "injury",
+            Harm::OldAge => "old_age",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Harm::OldAge => "old age",
+            _ => self.name(),
         }
     }
 }
 
+impl LifeStage {
+    pub fn name(&self) -> &'static str {
+        match self {
+            LifeStage::Child => "Child",
+            LifeStage::Adult => "Adult",
+            LifeStage::Elder => "Elder",
+        }
+    }
+
+    fn food_share(&self) -> f32 {
+        match self {
+            LifeStage::Child => 0.5,
+            LifeStage::Adult => 1.0,
+            LifeStage::Elder => 0.75,
+        }
+    }
+
+    fn speed_share(&self) -> f32 {
+        match self {
+            LifeStage::Child => 0.8,
+            LifeStage::Adult => 1.0,
+            LifeStage::Elder => 0.7,
+        }
+    }
+
+    // of a full day's work
+    fn work_share(&self) -> f32 {
+        match self {
+            LifeStage::Child => 0.0,
+            LifeStage::Adult => 1.0,
+            LifeStage::Elder => 0.5,
+        }
+    }
+}
+
+impl fmt::Display for Harm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Harm {
+    type Err = ();
+
+    fn from_str(name: &str) -> Result<Harm, ()> {
+        match name {
+            "starvation" => Ok(Harm::Starvation),
+            "thirst" => Ok(Harm::Thirst),
+            "exhaustion" => Ok(Harm::Exhaustion),
+            "illness" => Ok(Harm::Illness),
+            "injury" => Ok(Harm::Injury),
+            "old_age" => Ok(Harm::OldAge),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Hash for Human {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+        self.location.x.to_bits().hash(state);
+        self.location.y.to_bits().hash(state);
+        self.inventory_id.hash(state);
+        self.fatigue.to_bits().hash(state);
+        self.hunger.to_bits().hash(state);
+        self.thirst.to_bits().hash(state);
+        self.health.to_bits().hash(state);
+        self.illness_days.hash(state);
+        self.last_harm.hash(state);
+        self.age.hash(state);
+        self.born.hash(state);
+        self.biography.hash(state);
+        self.partner.hash(state);
+        self.household.hash(state);
+        self.relationships.hash(state);
+        self.personality.hash(state);
+        self.speed.to_bits().hash(state);
+        self.job.hash(state);
+    }
+}
+
 impl Mind {
-    pub fn new(home: Vector) -> Mind {
+    pub fn new() -> Mind {
         Mind {
             current_path: Vec::new(),
             state: Activity::Idle,
 
-            home: home,
-
             had_breakfast: false,
             had_dinner: false,
             meal_size: 0,
 
             target_inventory_id: None,
+            plan: Vec::new(),
+            replans: 0,
             progress: 0,
 
             wait: 0,
             travel_vector: None,
+
+            beliefs: Beliefs::new(),
+            knowledge: Knowledge::new(),
+            prayed_today: false,
+            days_since_prayer: PRAYED_RECENTLY_DAYS,
+            prayer_progress: 0,
+
+            target_market: None,
+            sell_quantity: 0,
+            mail: Vec::new(),
+            sold_today: false,
+            shopped_today: false,
+            took_money: false,
+            went_hungry: None,
+            socialised_today: false,
+            company: 0,
+            sleep_quality: 0.0,
+        }
+    }
+
+    /// Fails if a market they're headed for or planning to buy at isn't in `world`.
+    pub fn check_references(&self, world: &World) -> Result<(), SaveError> {
+        let markets = self.plan.iter().filter_map(|step| match step {
+            Step::Buy(market_index, _) => Some(*market_index),
+            _ => None,
+        });
+        for market_index in self.target_market.into_iter().chain(markets) {
+            if market_index >= world.markets.len() {
+                return Err(SaveError::dangling("market", market_index));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn beliefs(&self) -> &Beliefs {
+        &self.beliefs
+    }
+
+    pub fn knowledge(&self) -> &Knowledge {
+        &self.knowledge
+    }
+
+    pub fn description_lines(&self) -> Vec<String> {
+        let mut lines = vec![format!("State: {}", self.state())];
+        lines.push(format!("Piety: {:.2}", self.beliefs.piety()));
+        lines.append(&mut self.beliefs.description_lines());
+        lines.append(&mut self.knowledge.description_lines());
+        lines
+    }
+
+    /// Whether they're up for a chat: not asleep, or on their way to bed.
+    pub fn can_talk(&self) -> bool {
+        match self.state {
+            Activity::Sleeping => false,
+            _ => true,
+        }
+    }
+
+    /// Whether they've gone to spend time with a friend, and so have more to say than usual.
+    pub fn is_socialising(&self) -> bool {
+        match self.state {
+            Activity::Socialising => true,
+            _ => false,
+        }
+    }
+
+    /// What they'd tell someone they stopped to talk to.
+    pub fn gossip(&self) -> Gossip {
+        self.knowledge.gossip(&self.beliefs)
+    }
+
+    /// Takes in what `speaker` had to say, believing it as far as they trust them.
+    pub fn hear(&mut self, human: &Human, speaker: HumanId, gossip: &Gossip) {
+        let credence = HEARSAY_CREDENCE * (1.0 + human.relationships.trust(speaker)).max(0.0);
+        for &(belief, weight) in &gossip.opinions {
+            self.beliefs
+                .reinforce(belief, weight, PERSUASION * credence);
+        }
+        for &(container_id, report) in &gossip.stores {
+            self.knowledge.learn_store(container_id, report);
+        }
+        for record in &gossip.news {
+            if self.knowledge.learn_news(record) {
+                self.perceive(human, record, credence);
+            }
         }
     }
 
@@ -189,9 +856,16 @@ impl Mind {
             Activity::Sleeping => "Sleeping",
             Activity::Eating(EatingState::Eating) => "Eating",
             Activity::Eating(EatingState::Finding) => "Find Food",
+            Activity::Drinking(DrinkingState::Drinking) => "Drinking",
+            Activity::Drinking(DrinkingState::Finding) => "Find Water",
             Activity::Working(WorkState::Commuting) => "Work: Commuting",
             Activity::Working(WorkState::Working) => "Work: Working",
             Activity::Working(WorkState::Storing) => "Work: Storing",
+            Activity::Working(WorkState::Selling) => "Selling",
+            Activity::Praying => "Praying",
+            Activity::Shopping => "Shopping",
+            Activity::Socialising => "Socialising",
+            Activity::Wandering(_) => "Wandering",
         }
     }
 
@@ -201,27 +875,863 @@ impl Mind {
         self.current_path = geography.find_path(start, goal).unwrap_or(Vec::new());
     }
 
-    // TODO implement jobs (just farming first)
+    // done with this meal, whether or not there was anything to eat
+    fn skip_meal(&mut self) {
+        self.plan.clear();
+        self.replans = 0;
+        if !self.had_breakfast {
+            self.had_breakfast = true;
+        } else {
+            self.had_dinner = true;
+        }
+        self.state = Activity::Idle;
+    }
+
+    fn prayed_recently(&self) -> bool {
+        self.days_since_prayer < PRAYED_RECENTLY_DAYS
+    }
+
+    // longer for the diligent, and for those who think the harvest is down to them rather than
+    // the god
+    fn work_ticks(&self, human: &Human) -> u32 {
+        (WORK_TICKS as f32
+            * (1.0 + self.beliefs.self_reliance() / 2.0)
+            * (1.0 + DILIGENT_WORK * Personality::lean(human.personality.diligence))
+            * human.stage().work_share()) as u32
+    }
+
+    // the usual hour for something, brought forward for those keen on it and put off by those
+    // who aren't
+    fn routine_hour(hour: i32, keenness: f32) -> i32 {
+        hour - (TEMPERAMENT_HOURS * Personality::lean(keenness)).round() as i32
+    }
+
+    // whether they care enough to pray; the devout will whatever their doubts
+    fn wants_to_pray(&self, human: &Human) -> bool {
+        self.beliefs.piety() > PRAYER_PIETY * (1.0 - Personality::lean(human.personality.piety))
+    }
+
+    // whether it's time for a meal they've not had yet
+    fn meal_due(&self, situation: &Situation) -> bool {
+        let gluttony = situation.human.personality.gluttony;
+        (!self.had_breakfast && situation.hour > Mind::routine_hour(BREAKFAST_HOUR, gluttony))
+            || (!self.had_dinner && situation.hour > Mind::routine_hour(DINNER_HOUR, gluttony))
+    }
+
+    // whoever outside their household they're fondest of, if they like anyone
+    fn companion(&self, human: &Human, world: &World) -> Option<HumanId> {
+        human
+            .relationships
+            .iter()
+            .filter(|(_, relationship)| relationship.affection > 0.0)
+            .filter(|&(other, _)| {
+                world
+                    .humans
+                    .get(other)
+                    .map_or(false, |other| other.household != human.household)
+            })
+            .max_by(|a, b| a.1.affection.partial_cmp(&b.1.affection).unwrap())
+            .map(|(other, _)| other)
+    }
+
+    fn drink_utility(&self, situation: &Situation) -> f32 {
+        let thirst = situation.human.thirst;
+        let drinking = match self.state {
+            Activity::Drinking(_) => true,
+            _ => false,
+        };
+        if thirst > THIRSTY || (drinking && thirst > 0.0) {
+            DRINK_UTILITY + (1.0 - DRINK_UTILITY) * ramp(thirst, THIRSTY, PARCHED)
+        } else {
+            0.0
+        }
+    }
+
+    fn meal_utility(&self, situation: &Situation) -> f32 {
+        if self.meal_due(situation) {
+            MEAL_UTILITY + (0.95 - MEAL_UTILITY) * ramp(situation.human.hunger, 0.0, STARVING)
+        } else {
+            0.0
+        }
+    }
+
+    // once they've set out they see the day's work through, stores and market included
+    fn work_utility(&self, situation: &Situation) -> f32 {
+        let human = situation.human;
+        let at_work = match self.state {
+            Activity::Working(_) => true,
+            _ => false,
+        };
+        let due = situation.hour > Mind::routine_hour(WORK_HOUR, human.personality.diligence)
+            && self.progress <= self.work_ticks(human)
+            && !human.is_sick()
+            && human.stage() != LifeStage::Child;
+        if at_work || due {
+            WORK_UTILITY * (1.0 + Personality::lean(human.personality.diligence) / 10.0)
+        } else {
+            0.0
+        }
+    }
+
+    fn shop_utility(&self, situation: &Situation) -> f32 {
+        if !self.wants_to_shop(situation.human, situation.world) {
+            return 0.0;
+        }
+        let shopping = match self.state {
+            Activity::Shopping => true,
+            _ => false,
+        };
+        let open = situation.hour > MARKET_HOURS.0 && situation.hour < MARKET_HOURS.1;
+        if open || shopping {
+            SHOP_UTILITY
+        } else {
+            0.0
+        }
+    }
+
+    // whether it's between their bedtime and the hour they get up; the diligent stay up later
+    // and rise earlier
+    fn is_night(&self, situation: &Situation) -> bool {
+        let diligence = situation.human.personality.diligence;
+        situation.hour >= Mind::routine_hour(BEDTIME_HOUR, 1.0 - diligence)
+            || situation.hour < Mind::routine_hour(WAKE_HOUR, diligence)
+    }
+
+    // at night they sleep until it's time to get up, unless something more pressing wakes them;
+    // in the day only a nap will do, and only once they're worn out
+    fn sleep_utility(&self, situation: &Situation) -> f32 {
+        let human = situation.human;
+        let asleep = match self.state {
+            Activity::Sleeping => true,
+            _ => false,
+        };
+        if self.is_night(situation) {
+            if asleep || human.fatigue > DROWSY {
+                NIGHT_SLEEP_UTILITY
+                    + (0.95 - NIGHT_SLEEP_UTILITY) * ramp(human.fatigue, DROWSY, EXHAUSTED)
+            } else {
+                0.0
+            }
+        } else {
+            let nap =
+                NAP_FATIGUE + DILIGENT_FATIGUE * Personality::lean(human.personality.diligence);
+            if human.fatigue > nap || (asleep && human.fatigue > DROWSY) {
+                SLEEP_UTILITY + (0.95 - SLEEP_UTILITY) * ramp(human.fatigue, DROWSY, EXHAUSTED)
+            } else {
+                0.0
+            }
+        }
+    }
+
+    fn prayer_utility(&self, situation: &Situation) -> f32 {
+        let praying = match self.state {
+            Activity::Praying => true,
+            _ => false,
+        };
+        if praying
+            || (situation.hour > PRAYER_HOUR
+                && !self.prayed_today
+                && self.wants_to_pray(situation.human))
+        {
+            PRAYER_UTILITY
+        } else {
+            0.0
+        }
+    }
+
+    fn social_utility(&self, situation: &Situation) -> f32 {
+        let human = situation.human;
+        let socialising = self.is_socialising();
+        let free = situation.hour >= LEISURE_HOURS.0
+            && situation.hour < LEISURE_HOURS.1
+            && !self.socialised_today;
+        if (socialising || free) && self.companion(human, situation.world).is_some() {
+            SOCIAL_UTILITY * (1.0 + Personality::lean(human.personality.sociability))
+        } else {
+            0.0
+        }
+    }
+
+    fn wander_utility(&self, situation: &Situation) -> f32 {
+        let wandering = match self.state {
+            Activity::Wandering(_) => true,
+            _ => false,
+        };
+        let chance =
+            WANDER_CHANCE * (1.0 + Personality::lean(situation.human.personality.curiosity));
+        if wandering || situation.whim < chance {
+            WANDER_UTILITY
+        } else {
+            0.0
+        }
+    }
+
+    // weighs up everything they might do and takes up whatever they'd most like to, unless it's
+    // not enough of an improvement on carrying on with what they're already doing
+    fn choose(&mut self, situation: &Situation) {
+        let current = mem::discriminant(&self.state);
+        let mut carrying_on = 0.0;
+        let mut best: Option<(&Choice, f32)> = None;
+        for choice in CHOICES.iter() {
+            let utility = (choice.utility)(self, situation);
+            if mem::discriminant(&(choice.start)()) == current {
+                carrying_on = utility + PERSISTENCE;
+            } else if utility > best.map_or(0.0, |(_, best)| best) {
+                best = Some((choice, utility));
+            }
+        }
+        if let Some((choice, utility)) = best {
+            if utility > carrying_on {
+                self.interrupt();
+                self.state = (choice.start)();
+            }
+        }
+    }
+
+    // drops whatever they were in the middle of, keeping any progress made at work or prayer
+    fn interrupt(&mut self) {
+        self.current_path.clear();
+        self.plan.clear();
+        self.replans = 0;
+        self.target_inventory_id = None;
+        self.target_market = None;
+        self.took_money = false;
+        self.company = 0;
+        self.sleep_quality = 0.0;
+    }
+
+    fn wants_to_shop(&self, human: &Human, world: &World) -> bool {
+        !self.shopped_today
+            && human.owned_item_count(Item::MONEY, world) > 0
+            && world
+                .nearest_market(human.location)
+                .map_or(false, |market_index| {
+                    let market = &world.markets[market_index];
+                    world.items.goods().any(|item| {
+                        market.cheapest(item, &world.inventories).is_some()
+                            && human.owned_item_count(item, world)
+                                < value::wanted_stock(
+                                    human,
+                                    item,
+                                    market.going_price(item),
+                                    &world.items,
+                                )
+                    })
+                })
+    }
+
+    // the stall they'd rather buy `item` at, as (stall inventory id, price, stock): the cheapest,
+    // allowing a little extra for a seller they trust
+    fn preferred_offer(
+        &self,
+        human: &Human,
+        market: &Market,
+        item: Item,
+        world: &World,
+    ) -> Option<(InventoryId, f32, u32)> {
+        market
+            .offers(item, &world.inventories)
+            .into_iter()
+            .map(|(stall_inventory_id, seller_inventory_id, price, stock)| {
+                let trust = world
+                    .carrier(seller_inventory_id)
+                    .map_or(0.0, |seller| human.relationships.trust(seller));
+                let worth = price * (1.0 - TRUST_PREMIUM * trust);
+                (stall_inventory_id, price, stock, worth)
+            })
+            .min_by(|a, b| a.3.partial_cmp(&b.3).unwrap())
+            .map(|(stall_inventory_id, price, stock, _)| (stall_inventory_id, price, stock))
+    }
+
+    // the stall to buy at and the mail buying from it the first good in the catalogue they're
+    // short of, can afford, and is in stock
+    fn shopping(
+        &self,
+        human: &Human,
+        market: &Market,
+        world: &World,
+    ) -> Option<(InventoryId, Vec<Mail>)> {
+        let money = world.inventories[human.inventory_id].count(Item::MONEY);
+        world
+            .items
+            .goods()
+            .filter_map(|item| {
+                let (stall_inventory_id, price, stock) =
+                    self.preferred_offer(human, market, item, world)?;
+                let wanted = value::wanted_stock(human, item, price, &world.items)
+                    .saturating_sub(human.owned_item_count(item, world));
+                let affordable = (money as f32 / price).floor() as u32;
+                let quantity = wanted.min(stock).min(affordable);
+                let cost = ((quantity as f32 * price).ceil() as u32).min(money);
+                if quantity > 0 {
+                    Some((
+                        stall_inventory_id,
+                        market::purchase(
+                            human.inventory_id,
+                            stall_inventory_id,
+                            item,
+                            quantity,
+                            cost,
+                        ),
+                    ))
+                } else {
+                    None
+                }
+            })
+            .next()
+    }
+
+    // how much of what they're carrying is more than they'd want to keep at the going price
+    fn surplus(&self, human: &Human, world: &World) -> u32 {
+        world
+            .nearest_market(human.location)
+            .map_or(0, |market_index| {
+                let item = human.job.product(&world.items);
+                let price = world.markets[market_index].going_price(item);
+                human
+                    .owned_item_count(item, world)
+                    .saturating_sub(value::wanted_stock(human, item, price, &world.items))
+            })
+    }
+
+    // whether anyone in a household is a friend who'd spare them a meal
+    fn has_friend_in(&self, human: &Human, household_id: HouseholdId, world: &World) -> bool {
+        world
+            .households
+            .get(household_id)
+            .map_or(false, |household| {
+                household
+                    .members()
+                    .iter()
+                    .any(|&member| human.relationships.affection(member) >= FRIENDSHIP)
+            })
+    }
+
+    // money on them beyond what they keep in their pocket, unless they need it all for work
+    fn spare_money(&self, human: &Human, world: &World) -> u32 {
+        if human.job.keeps_money() {
+            0
+        } else {
+            world.inventories[human.inventory_id]
+                .count(Item::MONEY)
+                .saturating_sub(POCKET_MONEY)
+        }
+    }
+
+    // how much they'd eat at a sitting
+    fn helping(&self, human: &Human) -> u32 {
+        self.meal_size.min(human.hunger as u32).max(1)
+    }
+
+    // where somewhere a plan might take them is, if it's still there
+    fn place_location(&self, place: Place, human: &Human, world: &World) -> Option<Vector> {
+        match place {
+            Place::Home => Some(human.home(world)),
+            Place::Container(container_id) => world
+                .containers
+                .get(container_id)
+                .map(|container| container.location),
+            Place::Market(market_index) => world
+                .markets
+                .get(market_index)
+                .map(|market| market.location),
+        }
+    }
+
+    // the easiest way they know of to come by a meal: from their own stores, at the nearest
+    // market, fetching money from home to pay if need be, or from a friend's household they've
+    // heard has food to spare
+    fn plan_meal(&self, human: &Human, world: &World) -> Option<Vec<Step>> {
+        let helping = self.helping(human);
+        let carried = &world.inventories[human.inventory_id];
+        let mut facts = BTreeSet::new();
+        let mut actions = vec![Action::eat(true, 0.0), Action::eat(false, EATING_OUT)];
+        if carried.count(Item::FOOD) > 0 {
+            facts.insert(Fact::Carrying(Item::FOOD));
+        }
+
+        let mut price = None;
+        if let Some(market_index) = world.nearest_market(human.location) {
+            let market = &world.markets[market_index];
+            if let Some((_, offer, _)) = self.preferred_offer(human, market, Item::FOOD, world) {
+                let cost = (helping as f32 * offer).ceil() as u32;
+                if carried.count(Item::MONEY) >= cost {
+                    facts.insert(Fact::Carrying(Item::MONEY));
+                }
+                actions.push(Action::buy(market_index, Item::FOOD, BUYING_COST));
+                price = Some(cost);
+            }
+        }
+
+        // they can see what's in their own stores
+        for &container_id in world.households[human.household].containers() {
+            if let Some(container) = world.containers.get(container_id) {
+                let stock = &world.inventories[container.inventory_id];
+                if stock.count(Item::FOOD) > 0 {
+                    facts.insert(Fact::Stocked(container_id, Item::FOOD));
+                    actions.push(Action::take(container_id, Item::FOOD, 0.0));
+                }
+                let money = stock.count(Item::MONEY) + carried.count(Item::MONEY);
+                if price.map_or(false, |cost| money >= cost) {
+                    facts.insert(Fact::Stocked(container_id, Item::MONEY));
+                    actions.push(Action::take(container_id, Item::MONEY, 0.0));
+                }
+            }
+        }
+
+        // and have heard about other people's
+        for (container_id, report) in self.knowledge.stores() {
+            if report.food > 0
+                && report.household != human.household
+                && self.has_friend_in(human, report.household, world)
+            {
+                facts.insert(Fact::Stocked(container_id, Item::FOOD));
+                actions.push(Action::take(container_id, Item::FOOD, NEIGHBOURLY_COST));
+            }
+        }
+
+        self.plan_for(facts, &[Fact::Fed], &actions, human, world)
+    }
+
+    // where to put away what they've brought back from work, and any money they can spare:
+    // whichever of their household's containers has room and is least out of the way
+    fn plan_storage(&self, human: &Human, world: &World) -> Option<Vec<Step>> {
+        let carried = &world.inventories[human.inventory_id];
+        let mut items: Vec<Item> = human
+            .job
+            .work_items(&world.items)
+            .into_iter()
+            .filter(|&item| carried.count(item) > 0)
+            .collect();
+        if self.spare_money(human, world) > 0 {
+            items.push(Item::MONEY);
+        }
+
+        let mut facts = BTreeSet::new();
+        let mut actions = Vec::new();
+        for &container_id in world.households[human.household].containers() {
+            if let Some(container) = world.containers.get(container_id) {
+                let stock = &world.inventories[container.inventory_id];
+                for &item in &items {
+                    if stock.room_for(item, &world.items) > 0 {
+                        facts.insert(Fact::HasSpace(container_id));
+                        actions.push(Action::store(container_id, item, 0.0));
+                    }
+                }
+            }
+        }
 
-    // TODO function to calculate value of items based on how much you have stored (exponential decay)
-    // only want Wood if you're a crafter
+        // whatever there's no room for anywhere they'll just have to keep carrying
+        let mut goal = Vec::new();
+        for item in items {
+            if actions
+                .iter()
+                .any(|action| action.gives.contains(&Fact::Stored(item)))
+            {
+                facts.insert(Fact::Carrying(item));
+                goal.push(Fact::Stored(item));
+            }
+        }
+        if goal.is_empty() {
+            return None;
+        }
+        self.plan_for(facts, &goal, &actions, human, world)
+    }
+
+    // how to get hold of `tool` for work, from their household's stores
+    fn plan_tool(&self, human: &Human, tool: Item, world: &World) -> Option<Vec<Step>> {
+        let mut facts = BTreeSet::new();
+        let mut actions = Vec::new();
+        for &container_id in world.households[human.household].containers() {
+            if let Some(container) = world.containers.get(container_id) {
+                if world.inventories[container.inventory_id].count(tool) > 0 {
+                    facts.insert(Fact::Stocked(container_id, tool));
+                    actions.push(Action::pick_up(container_id, tool, 0.0));
+                }
+            }
+        }
+        self.plan_for(facts, &[Fact::Holding(tool)], &actions, human, world)
+    }
 
-    // TODO go sell at market if you have surplus of the thing you make
-    // TODO go buy at market if you have money and free time
-    // (hard-coded market location for now)
+    // the cheapest way to `goal` from `facts`, which gains wherever they're standing already
+    fn plan_for(
+        &self,
+        mut facts: BTreeSet<Fact>,
+        goal: &[Fact],
+        actions: &[Action],
+        human: &Human,
+        world: &World,
+    ) -> Option<Vec<Step>> {
+        let tile = TilePoint::from_vector(&human.location);
+        for action in actions {
+            for fact in &action.needs {
+                if let Fact::At(place) = fact {
+                    let there = self.place_location(*place, human, world);
+                    if there.map_or(false, |there| TilePoint::from_vector(&there) == tile) {
+                        facts.insert(*fact);
+                    }
+                }
+            }
+        }
+        plan::plan(&facts, goal, actions, human.location, |place| {
+            self.place_location(place, human, world)
+                .unwrap_or(human.location)
+        })
+    }
 
-    // TODO when selling, a store has X quanitity to sell and wants to sell it within Y hours.
-    // every minute or so, the store will update it's prices so that it is on target to just barely
-    // go out of stock at the end of time. This, and assuming buyers buy the (nearly) cheapest
-    // goods should simulate supply and demand economics well enough
+    // carries out the next step of their plan; false if it's fallen through
+    fn follow_plan(&mut self, human: &Human, world: &World) -> bool {
+        let step = match self.plan.first() {
+            Some(&step) => step,
+            None => return false,
+        };
+        let carried = &world.inventories[human.inventory_id];
+        let done = match step {
+            Step::Walk(place) => match self.place_location(place, human, world) {
+                Some(location) => {
+                    if TilePoint::from_vector(&location) == TilePoint::from_vector(&human.location)
+                    {
+                        true
+                    } else {
+                        self.set_goal(human, location, &world.geography);
+                        if self.current_path.is_empty() {
+                            return false; // no way there
+                        }
+                        false
+                    }
+                }
+                None => {
+                    if let Place::Container(container_id) = place {
+                        self.knowledge.forget_store(container_id);
+                    }
+                    return false;
+                }
+            },
+            Step::Take(container_id, item) => match world.containers.get(container_id) {
+                Some(container) if world.inventories[container.inventory_id].count(item) > 0 => {
+                    let quantity = if item == Item::FOOD {
+                        self.helping(human)
+                    } else if item == Item::MONEY {
+                        POCKET_MONEY.saturating_sub(carried.count(item)).max(1)
+                    } else {
+                        1 // a tool
+                    };
+                    self.mail.push(Mail::new(
+                        container.inventory_id,
+                        ItemMessage::Take(item, quantity, human.inventory_id),
+                    ));
+                    true
+                }
+                _ => return false,
+            },
+            Step::Store(container_id, item) => match world.containers.get(container_id) {
+                Some(container) => {
+                    let quantity = if item == Item::MONEY {
+                        self.spare_money(human, world)
+                    } else {
+                        carried.count(item)
+                    };
+                    if quantity > 0 {
+                        self.mail.push(Mail::new(
+                            human.inventory_id,
+                            ItemMessage::Transfer(
+                                human.inventory_id,
+                                item,
+                                quantity,
+                                container.inventory_id,
+                            ),
+                        ));
+                    }
+                    true
+                }
+                None => return false,
+            },
+            Step::Buy(market_index, item) => {
+                let market = match world.markets.get(market_index) {
+                    Some(market) => market,
+                    None => return false,
+                };
+                let money = carried.count(Item::MONEY);
+                match self.preferred_offer(human, market, item, world) {
+                    Some((stall_inventory_id, price, stock)) => {
+                        let quantity = self
+                            .helping(human)
+                            .min(stock)
+                            .min((money as f32 / price).floor() as u32);
+                        if quantity == 0 {
+                            return false;
+                        }
+                        let cost = ((quantity as f32 * price).ceil() as u32).min(money);
+                        self.mail = market::purchase(
+                            human.inventory_id,
+                            stall_inventory_id,
+                            item,
+                            quantity,
+                            cost,
+                        );
+                        self.target_market = Some(market_index);
+                        self.target_inventory_id = Some(stall_inventory_id);
+                        true
+                    }
+                    None => return false,
+                }
+            }
+            Step::Eat => {
+                if carried.count(Item::FOOD) == 0 {
+                    return false;
+                }
+                self.state = Activity::Eating(EatingState::Eating);
+                true
+            }
+        };
+        if done {
+            self.plan.remove(0);
+        }
+        true
+    }
 
-    pub fn think(&mut self, human: &Human, world: &World) {
+    // head for the nearest household container holding `item` and take some out once there;
+    // false if there's none anywhere
+    fn fetch(&mut self, human: &Human, item: Item, quantity: u32, world: &World) -> bool {
+        let container = human
+            .containers(world)
+            .into_iter()
+            .find(|container| world.inventories[container.inventory_id].count(item) > 0);
+        if let Some(container) = container {
+            if TilePoint::from_vector(&container.location)
+                == TilePoint::from_vector(&human.location)
+            {
+                self.mail = vec![Mail::new(
+                    container.inventory_id,
+                    ItemMessage::Take(item, quantity, human.inventory_id),
+                )];
+            } else {
+                self.set_goal(human, container.location, &world.geography);
+            }
+            true
+        } else {
+            false
+        }
+    }
+
+    // follows a plan to fetch a tool their work needs and they haven't got to hand; false once
+    // they've got it, or there's no getting it and they'll go to work without
+    fn fetching_tool(&mut self, human: &Human, world: &World) -> bool {
+        let tool = match human.job.tool(&world.items) {
+            Some(tool) => tool,
+            None => return false,
+        };
+        let at_work = human
+            .job
+            .work_inventory(world)
+            .map_or(false, |work_inventory_id| {
+                world.inventories[work_inventory_id].count(tool) > 0
+            });
+        if at_work || world.inventories[human.inventory_id].count(tool) > 0 {
+            self.plan.clear();
+            self.replans = 0;
+            return false;
+        }
+        if self.plan.is_empty() && self.replans <= MAX_REPLANS {
+            self.plan = self.plan_tool(human, tool, world).unwrap_or(Vec::new());
+        }
+        if self.plan.is_empty() {
+            // no way of getting one, so they'll manage without
+            self.replans = 0;
+            return false;
+        }
+        if !self.follow_plan(human, world) {
+            // it fell through; think again
+            self.plan.clear();
+            self.current_path.clear();
+            self.replans += 1;
+        }
+        true
+    }
+
+    // the nearest shrine, or home if there are none
+    fn prayer_location(&self, human: &Human, world: &World) -> Vector {
+        world
+            .shrines
+            .iter()
+            .map(|shrine| shrine.location)
+            .min_by(|a, b| {
+                human
+                    .location
+                    .distance(*a)
+                    .partial_cmp(&human.location.distance(*b))
+                    .unwrap_or(Ordering::Equal)
+            })
+            .unwrap_or(human.home(world))
+    }
+
+    /// Divine power this human gives the god this tick: prayer, and thanks for a good harvest
+    /// on a full stomach.
+    pub fn devotion(&self, human: &Human, world: &World) -> f32 {
+        let piety = self.beliefs.piety();
+        let mut devotion = 0.0;
+        if let Activity::Praying = self.state {
+            if self.current_path.is_empty() {
+                let at_shrine = world.shrines.iter().any(|shrine| {
+                    TilePoint::from_vector(&shrine.location)
+                        == TilePoint::from_vector(&human.location)
+                });
+                devotion += DEVOTION_PER_PRAYER_TICK
+                    * piety
+                    * if at_shrine { SHRINE_DEVOTION } else { 1.0 };
+            }
+        }
+        if human.hunger < SATED_HUNGER {
+            let now = world.time.ticks_elapsed();
+            let harvests = world
+                .events
+                .iter()
+                .filter(|record| record.tick == now)
+                .filter(|record| match record.event {
+                    WorldEvent::GoodHarvest(crop_id, _) => human.works_crop(crop_id),
+                    _ => false,
+                })
+                .count();
+            devotion += harvests as f32 * GRATITUDE * piety;
+        }
+        devotion
+    }
+
+    // `credence` is how far they take it in, 1 for seeing it with their own eyes
+    fn perceive(&mut self, human: &Human, record: &EventRecord, credence: f32) {
+        let own_crop = record
+            .event
+            .crop_id()
+            .map_or(false, |crop_id| human.works_crop(crop_id));
+        // what happens to your own field is more convincing than what you see over the fence
+        let strength = (if own_crop { 1.0 } else { 0.5 }) * credence;
+        match record.event {
+            WorldEvent::CropFailed(_) => {
+                if self.prayed_recently() {
+                    // prayers went unanswered
+                    self.beliefs
+                        .reinforce(Belief::GodGrantsHarvest, -1.0, 0.05 * strength);
+                    self.beliefs
+                        .reinforce(Belief::GodIsBenevolent, -1.0, 0.1 * strength);
+                } else {
+                    // the god was neglected and is punishing us
+                    self.beliefs
+                        .reinforce(Belief::GodGrantsHarvest, 1.0, 0.05 * strength);
+                }
+            }
+            WorldEvent::GoodHarvest(_, _) => {
+                if self.prayed_recently() {
+                    self.beliefs
+                        .reinforce(Belief::GodGrantsHarvest, 1.0, 0.1 * strength);
+                    self.beliefs
+                        .reinforce(Belief::GodIsBenevolent, 1.0, 0.05 * strength);
+                    self.beliefs
+                        .reinforce(Belief::GodExists, 1.0, 0.02 * strength);
+                } else {
+                    // grew just fine without any praying
+                    self.beliefs
+                        .reinforce(Belief::GodGrantsHarvest, -1.0, 0.05 * strength);
+                }
+            }
+            WorldEvent::Birth => {
+                if self.prayed_recently() {
+                    // a blessing
+                    self.beliefs
+                        .reinforce(Belief::GodIsBenevolent, 1.0, 0.05 * credence);
+                }
+            }
+            WorldEvent::Death(_) => {
+                // a god who cared wouldn't have let it happen, least of all to the faithful
+                let strength = if self.prayed_recently() { 0.1 } else { 0.03 };
+                self.beliefs
+                    .reinforce(Belief::GodIsBenevolent, -1.0, strength * credence);
+            }
+            WorldEvent::Rain(_) => {
+                if self.prayed_recently() {
+                    self.beliefs
+                        .reinforce(Belief::GodControlsWeather, 1.0, 0.05 * credence);
+                }
+            }
+            WorldEvent::Miracle(miracle) => {
+                // seeing is believing; hearing about it, less so
+                self.beliefs
+                    .reinforce(Belief::GodExists, 1.0, 0.3 * credence);
+                let kindness = if miracle.is_benevolent() { 1.0 } else { -1.0 };
+                self.beliefs
+                    .reinforce(Belief::GodIsBenevolent, kindness, 0.1 * credence);
+                match miracle {
+                    Miracle::Rain | Miracle::Sunshine => {
+                        self.beliefs
+                            .reinforce(Belief::GodControlsWeather, 1.0, 0.2 * credence);
+                    }
+                    Miracle::BlessCrop(_) => {
+                        self.beliefs
+                            .reinforce(Belief::GodGrantsHarvest, 1.0, 0.2 * strength);
+                    }
+                    _ => (),
+                }
+            }
+        }
+    }
+
+    pub fn think<R: Rng>(&mut self, human_id: HumanId, human: &Human, world: &World, rng: &mut R) {
         // percieve
         if world.time.is_new_day() {
-            self.meal_size = human.daily_food(world) / 2;
+            // fearful people keep more back for later
+            let meal_size = human.daily_food(world) as f32 / 2.0;
+            self.meal_size = (meal_size * (1.0 - self.beliefs.fear() / 4.0)) as u32;
             self.had_breakfast = false;
             self.had_dinner = false;
             self.progress = 0;
+            self.prayed_today = false;
+            self.days_since_prayer += 1;
+            self.sold_today = false;
+            self.shopped_today = false;
+            self.took_money = false;
+            self.socialised_today = false;
+            self.knowledge.forget_old_news(world.time.ticks_elapsed());
+        }
+        let now = world.time.ticks_elapsed();
+        for record in world.events.iter().filter(|record| record.tick == now) {
+            let noticed = match record.location {
+                Some(location) => {
+                    location.distance(human.location) <= PERCEPTION_RADIUS
+                        || record
+                            .event
+                            .crop_id()
+                            .map_or(false, |crop_id| human.works_crop(crop_id))
+                }
+                None => true,
+            };
+            if noticed {
+                self.knowledge.learn_news(record);
+                self.perceive(human, record, 1.0);
+            }
+        }
+        // they can see for themselves what's in their own stores, and in any they're standing at
+        let tile = TilePoint::from_vector(&human.location);
+        for (container_id, container) in world.containers.iter() {
+            let household_id = if world.households[human.household]
+                .containers()
+                .contains(&container_id)
+            {
+                Some(human.household)
+            } else if TilePoint::from_vector(&container.location) == tile {
+                world.container_owner(container_id)
+            } else {
+                None
+            };
+            if let Some(household_id) = household_id {
+                self.knowledge.learn_store(
+                    container_id,
+                    StoreReport {
+                        location: container.location,
+                        household: household_id,
+                        food: world.inventories[container.inventory_id].count(Item::FOOD),
+                        seen: now,
+                    },
+                );
+            }
         }
 
         // think
@@ -229,144 +1739,319 @@ impl Mind {
             self.wait -= 1;
         }
         if self.wait == 0 {
+            let situation = Situation {
+                human: human,
+                world: world,
+                hour: world.time.hour as i32,
+                whim: rng.gen(),
+            };
+            self.choose(&situation);
             match &self.state {
-                Activity::Idle => {
-                    let current_hours = world.time.hour;
-                    if current_hours > 6 && !self.had_breakfast {
-                        self.state = Activity::Eating(EatingState::Finding);
-                    } else if current_hours > 7 && self.progress == 0 {
-                        self.state = Activity::Working(WorkState::Commuting);
-                    } else if current_hours > 17 && !self.had_dinner {
-                        self.state = Activity::Eating(EatingState::Finding);
-                    } else if human.fatigue > 80.0 {
-                        // TODO sleep based on time of day
-                        self.state = Activity::Sleeping;
-                    } else if self.current_path.is_empty() {
-                        let mut rng = thread_rng();
-                        if rng.gen::<f32>() > 0.99 {
-                            let normal = Normal::new(0.0, 5.0);
-                            self.set_goal(
-                                human,
-                                human.location
-                                    + Vector::new(
-                                        normal.sample(&mut rng) as f32,
-                                        normal.sample(&mut rng) as f32,
-                                    ),
-                                &world.geography,
-                            );
-                        }
-                    }
-                }
+                Activity::Idle => (),
 
                 Activity::Eating(eating_state) => match eating_state {
                     EatingState::Finding => {
+                        if human.hunger < 1.0 {
+                            // not hungry after all
+                            self.skip_meal();
+                        } else if self.current_path.is_empty() {
+                            if self.plan.is_empty() {
+                                self.plan = self.plan_meal(human, world).unwrap_or(Vec::new());
+                            }
+                            if self.plan.is_empty() || self.replans > MAX_REPLANS {
+                                // nothing to be had, so go without
+                                self.went_hungry = Some(world.time.today());
+                                self.skip_meal();
+                            } else if !self.follow_plan(human, world) {
+                                // it fell through; think again
+                                self.plan.clear();
+                                self.current_path.clear();
+                                self.replans += 1;
+                            }
+                        }
+                    }
+                    EatingState::Eating => {
+                        // anything more they're carrying is for selling or later
+                        if world.inventories[human.inventory_id].count(Item::FOOD) == 0
+                            || human.hunger <= 0.0
+                        {
+                            self.skip_meal();
+                        }
+                    }
+                },
+
+                Activity::Drinking(drinking_state) => match drinking_state {
+                    DrinkingState::Finding => {
                         if self.current_path.is_empty() {
-                            if world.inventories[human.inventory_id].count(Item::Food) == 0 {
-                                let mut food_containers: Vec<&Container> = human
-                                    .owned_container_indeces
-                                    .iter()
-                                    .map(|&i| &world.containers[i])
-                                    .filter(|&container| {
-                                        world.inventories[container.inventory_id].count(Item::Food)
+                            if world.inventories[human.inventory_id].count(Item::WATER) == 0 {
+                                let draught = (human.thirst / WATER_QUENCH).ceil() as u32;
+                                let water_container =
+                                    human.containers(world).into_iter().find(|container| {
+                                        world.inventories[container.inventory_id].count(Item::WATER)
                                             > 0
-                                    })
-                                    .collect();
-                                food_containers.sort_by_key(|container| {
-                                    MinFloat(human.location.distance(container.location))
-                                });
-                                if let Some(container) = food_containers.first() {
+                                    });
+                                if let Some(container) = water_container {
                                     if TilePoint::from_vector(&container.location)
                                         == TilePoint::from_vector(&human.location)
                                     {
-                                        self.target_inventory_id = Some(container.inventory_id);
+                                        self.mail.push(Mail {
+                                            inventory_id: container.inventory_id,
+                                            message: ItemMessage::Take(
+                                                Item::WATER,
+                                                draught,
+                                                human.inventory_id,
+                                            ),
+                                        });
                                     } else {
                                         self.set_goal(human, container.location, &world.geography);
                                     }
+                                } else if let Some(water) = world.nearest_water(human.location) {
+                                    if TilePoint::from_vector(&water)
+                                        == TilePoint::from_vector(&human.location)
+                                    {
+                                        // draw enough to carry some home
+                                        self.mail.push(Mail {
+                                            inventory_id: human.inventory_id,
+                                            message: ItemMessage::GiveOrDrop(
+                                                Item::WATER,
+                                                draught.max(WATER_DRAW),
+                                            ),
+                                        });
+                                    } else {
+                                        self.set_goal(human, water, &world.geography);
+                                    }
+                                } else {
+                                    // nowhere to drink from
+                                    self.state = Activity::Idle;
                                 }
                             } else {
-                                self.state = Activity::Eating(EatingState::Eating);
+                                self.state = Activity::Drinking(DrinkingState::Drinking);
                             }
                         }
                     }
-                    EatingState::Eating => {
-                        if world.inventories[human.inventory_id].count(Item::Food) == 0 {
-                            if !self.had_breakfast {
-                                self.had_breakfast = true;
-                            } else {
-                                self.had_dinner = true;
-                            }
+                    DrinkingState::Drinking => {
+                        if world.inventories[human.inventory_id].count(Item::WATER) == 0
+                            || human.thirst <= 0.0
+                        {
                             self.state = Activity::Idle;
                         }
                     }
                 },
 
-                Activity::Working(work_state) => match &human.job {
-                    Job::Farmer(crop_id) => match work_state {
-                        WorkState::Commuting => {
-                            if self.current_path.is_empty() {
-                                let crop = &world.crops[*crop_id];
-                                if TilePoint::from_vector(&human.location)
-                                    != TilePoint::from_vector(&crop.location)
-                                {
-                                    self.set_goal(human, crop.location, &world.geography);
+                Activity::Working(work_state) => match work_state {
+                    WorkState::Commuting => {
+                        if self.current_path.is_empty() {
+                            match human.job.workplace(human, world) {
+                                Some(workplace) => {
+                                    if TilePoint::from_vector(&human.location)
+                                        == TilePoint::from_vector(&workplace)
+                                    {
+                                        self.state = Activity::Working(WorkState::Working);
+                                        self.mail = human.job.start_work(human, world);
+                                    } else if !self.fetching_tool(human, world) {
+                                        self.set_goal(human, workplace, &world.geography);
+                                    }
+                                }
+                                None => {
+                                    // nowhere to work today
+                                    self.progress = self.work_ticks(human) + 1;
+                                    self.state = Activity::Idle;
+                                }
+                            }
+                        }
+                    }
+                    WorkState::Working => {
+                        // TODO wander around work tile
+                        if self.progress > self.work_ticks(human) {
+                            self.mail = human.job.finish_work(human, world, self.progress);
+                            self.state = Activity::Working(WorkState::Storing);
+                        } else {
+                            self.mail = human.job.work(human, world);
+                        }
+                    }
+                    WorkState::Storing => {
+                        if self.current_path.is_empty() {
+                            let carried = human.job.work_items(&world.items).iter().fold(
+                                0,
+                                |carried, &item| {
+                                    carried + world.inventories[human.inventory_id].count(item)
+                                },
+                            );
+                            let product = human.job.product(&world.items);
+                            let inventory = &world.inventories[human.inventory_id];
+                            let spare_money = self.spare_money(human, world);
+                            let carried_product = inventory.count(product);
+                            let to_sell = self
+                                .surplus(human, world)
+                                .min(carried_product + inventory.room_for(product, &world.items));
+                            if !self.sold_today && to_sell > 0 {
+                                if carried_product >= to_sell {
+                                    self.sell_quantity = to_sell;
+                                    self.state = Activity::Working(WorkState::Selling);
                                 } else {
-                                    self.state = Activity::Working(WorkState::Working);
-                                    self.target_inventory_id = Some(crop.inventory_id);
+                                    // fetch the rest of what's to be sold from their stores
+                                    self.fetch(human, product, to_sell - carried_product, world);
+                                }
+                            } else if carried != 0 || spare_money > 0 {
+                                if self.plan.is_empty() {
+                                    self.plan =
+                                        self.plan_storage(human, world).unwrap_or(Vec::new());
                                 }
+                                if self.plan.is_empty() || self.replans > MAX_REPLANS {
+                                    // nowhere to keep it, so keep carrying it
+                                    self.plan.clear();
+                                    self.replans = 0;
+                                    self.state = Activity::Idle;
+                                } else if !self.follow_plan(human, world) {
+                                    // it fell through; think again
+                                    self.plan.clear();
+                                    self.current_path.clear();
+                                    self.replans += 1;
+                                }
+                            } else {
+                                self.replans = 0;
+                                self.state = Activity::Idle;
                             }
                         }
-                        WorkState::Working => {
-                            // TODO wander around crop tile
-                            if self.progress > TICKS_PER_MINUTE as u32 * 60 * 6 {
-                                // work 6 hours / day
-                                self.state = Activity::Working(WorkState::Storing);
-                                self.target_inventory_id = None;
+                    }
+                    WorkState::Selling => {
+                        if self.current_path.is_empty() {
+                            match world.nearest_market(human.location) {
+                                Some(market_index) => {
+                                    let location = world.markets[market_index].location;
+                                    if TilePoint::from_vector(&location)
+                                        == TilePoint::from_vector(&human.location)
+                                    {
+                                        self.target_market = Some(market_index);
+                                    } else {
+                                        self.set_goal(human, location, &world.geography);
+                                    }
+                                }
+                                None => self.state = Activity::Working(WorkState::Storing),
                             }
                         }
-                        WorkState::Storing => {
-                            if self.current_path.is_empty() {
-                                if world.inventories[human.inventory_id].count(Item::Food) != 0 {
-                                    // TODO find an container with enough space. or at least exclude
-                                    // containers with no space
-                                    let mut food_containers: Vec<&Container> = human
-                                        .owned_container_indeces
-                                        .iter()
-                                        .map(|&i| &world.containers[i])
-                                        .collect();
-                                    food_containers.sort_by_key(|container| {
-                                        MinFloat(human.location.distance(container.location))
-                                    });
-                                    if let Some(container) = food_containers.first() {
-                                        if TilePoint::from_vector(&container.location)
-                                            == TilePoint::from_vector(&human.location)
-                                        {
-                                            self.target_inventory_id = Some(container.inventory_id);
-                                        } else {
-                                            self.set_goal(
-                                                human,
-                                                container.location,
-                                                &world.geography,
-                                            );
+                    }
+                },
+
+                Activity::Shopping => {
+                    let money = world.inventories[human.inventory_id].count(Item::MONEY);
+                    if !self.current_path.is_empty() {
+                        // on the way
+                    } else if !self.took_money && money < POCKET_MONEY {
+                        // take some of the household's money along, if there's any at home
+                        if !self.fetch(human, Item::MONEY, POCKET_MONEY - money, world)
+                            || !self.mail.is_empty()
+                        {
+                            self.took_money = true;
+                        }
+                    } else {
+                        match world.nearest_market(human.location) {
+                            Some(market_index) => {
+                                let market = &world.markets[market_index];
+                                if TilePoint::from_vector(&market.location)
+                                    == TilePoint::from_vector(&human.location)
+                                {
+                                    match self.shopping(human, market, world) {
+                                        Some((stall_inventory_id, mail)) => {
+                                            self.mail = mail;
+                                            self.target_market = Some(market_index);
+                                            self.target_inventory_id = Some(stall_inventory_id);
+                                        }
+                                        None => {
+                                            self.shopped_today = true;
+                                            self.took_money = false;
+                                            self.state = Activity::Idle;
                                         }
                                     }
                                 } else {
-                                    self.state = Activity::Idle;
+                                    self.set_goal(human, market.location, &world.geography);
                                 }
                             }
+                            None => {
+                                self.took_money = false;
+                                self.state = Activity::Idle;
+                            }
                         }
-                    },
-                },
+                    }
+                }
 
-                Activity::Sleeping => {
-                    if human.fatigue <= 0.0 {
+                Activity::Praying => {
+                    let prayer_location = self.prayer_location(human, world);
+                    if self.prayer_progress >= PRAYER_TICKS {
+                        self.prayer_progress = 0;
+                        self.prayed_today = true;
+                        self.days_since_prayer = 0;
                         self.state = Activity::Idle;
-                    } else if TilePoint::from_vector(&self.home)
+                    } else if TilePoint::from_vector(&prayer_location)
                         != TilePoint::from_vector(&human.location)
                         && self.current_path.is_empty()
                     {
-                        self.set_goal(human, self.home, &world.geography);
+                        self.set_goal(human, prayer_location, &world.geography);
+                    }
+                }
+
+                Activity::Sleeping => {
+                    let bed = world.bed(human_id);
+                    let home = bed.map_or(human.home(world), |bed| bed.location);
+                    if self.sleep_utility(&situation) == 0.0 {
+                        self.sleep_quality = 0.0;
+                        self.state = Activity::Idle;
+                    } else if self.sleep_quality > 0.0 {
+                        // already lain down
+                    } else if TilePoint::from_vector(&home)
+                        == TilePoint::from_vector(&human.location)
+                    {
+                        self.current_path.clear();
+                        self.sleep_quality = bed.map_or(FLOOR_QUALITY, |_| BED_QUALITY);
+                    } else if human.fatigue > EXHAUSTED {
+                        self.current_path.clear();
+                        self.sleep_quality = ROUGH_QUALITY;
+                    } else if self.current_path.is_empty() {
+                        self.set_goal(human, home, &world.geography);
                     }
                 }
+
+                Activity::Socialising => match self.companion(human, world) {
+                    Some(friend) => {
+                        let there = world.humans[friend].location;
+                        if TilePoint::from_vector(&there)
+                            .is_adjacent(&TilePoint::from_vector(&human.location))
+                        {
+                            // in company; there's talk enough to be had without going anywhere
+                            self.current_path.clear();
+                            self.company += 1;
+                            if self.company >= SOCIAL_TICKS {
+                                self.company = 0;
+                                self.socialised_today = true;
+                                self.state = Activity::Idle;
+                            }
+                        } else if self.current_path.is_empty() {
+                            self.set_goal(human, there, &world.geography);
+                        }
+                    }
+                    None => {
+                        self.socialised_today = true;
+                        self.state = Activity::Idle;
+                    }
+                },
+
+                Activity::Wandering(wander_state) => match wander_state {
+                    WanderState::Starting => {
+                        let normal = Normal::new(0.0, WANDER_DISTANCE);
+                        self.set_goal(
+                            human,
+                            human.location
+                                + Vector::new(normal.sample(rng) as f32, normal.sample(rng) as f32),
+                            &world.geography,
+                        );
+                        self.state = Activity::Wandering(WanderState::Walking);
+                    }
+                    WanderState::Walking => {
+                        if self.current_path.is_empty() {
+                            self.state = Activity::Idle;
+                        }
+                    }
+                },
             }
             self.update_travel(human);
         }
@@ -382,13 +2067,14 @@ impl Mind {
 
         if let Some(next_tile) = self.current_path.last() {
             let current_tile = TilePoint::from_vector(&human.location);
-            // aim for middle of nearest edge for smoother pathing
+            // aim for middle of nearest edge for smoother pathing; coming from the right or below,
+            // just inside it, since the edge itself still belongs to the current tile
             let goal = if current_tile.x > next_tile.x {
-                Vector::new(next_tile.x as f32 + 1.0, next_tile.y as f32 + 0.5)
+                Vector::new(next_tile.x as f32 + 0.95, next_tile.y as f32 + 0.5)
             } else if current_tile.x < next_tile.x {
                 Vector::new(next_tile.x as f32, next_tile.y as f32 + 0.5)
             } else if current_tile.y > next_tile.y {
-                Vector::new(next_tile.x as f32 + 0.5, next_tile.y as f32 + 1.0)
+                Vector::new(next_tile.x as f32 + 0.5, next_tile.y as f32 + 0.95)
             } else {
                 Vector::new(next_tile.x as f32 + 0.5, next_tile.y as f32 + 0.0)
             };
@@ -398,85 +2084,332 @@ impl Mind {
         }
     }
 
-    pub fn act(&mut self, human: &mut Human, inventory_senders: &Vec<Sender<ItemMessage>>) {
+    pub fn act(
+        &mut self,
+        human: &mut Human,
+        items: &ItemCatalogue,
+        inventory_senders: &Senders,
+        market_sender: &Sender<MarketMessage>,
+    ) {
+        if let Some(day) = self.went_hungry.take() {
+            human.note(day, LifeEvent::MissedMeal);
+        }
         if self.wait == 0 {
+            let sent_mail = !self.mail.is_empty();
+            for mail in self.mail.drain(..) {
+                inventory_senders.send(mail.inventory_id, mail.message);
+            }
             match &self.state {
                 Activity::Idle => (),
 
                 Activity::Eating(eating_state) => match eating_state {
                     EatingState::Eating => {
-                        inventory_senders[human.inventory_id]
-                            .send(ItemMessage::Remove(Item::Food, 1));
+                        inventory_senders
+                            .send(human.inventory_id, ItemMessage::Remove(Item::FOOD, 1));
                         human.hunger -= 1.0;
                     }
                     EatingState::Finding => {
-                        if let Some(target_inventory_id) = self.target_inventory_id {
-                            inventory_senders[target_inventory_id].send(ItemMessage::Take(
-                                Item::Food,
-                                self.meal_size.min(human.hunger as u32),
-                                human.inventory_id,
-                            ));
-                            self.target_inventory_id = None;
+                        self.report_purchase(human, market_sender);
+                        // wait for whatever they took or bought to arrive
+                        if sent_mail {
                             self.wait = 2;
                         }
                     }
                 },
 
-                Activity::Working(work_state) => match &human.job {
-                    Job::Farmer(_) => match work_state {
-                        WorkState::Commuting => (), // let travel do the work
-                        WorkState::Working => {
-                            self.progress += 1;
-                            if self.progress > TICKS_PER_MINUTE as u32 * 60 * 6 {
-                                // work 6 hours / day
-                                if let Some(target_inventory_id) = self.target_inventory_id {
-                                    inventory_senders[target_inventory_id].send(ItemMessage::Take(
-                                        Item::Food,
-                                        50,
-                                        human.inventory_id,
-                                    )); // TODO calculate remaining capacity in think/perceive and use that
-                                    self.target_inventory_id = None;
-                                    self.wait = 2;
-                                } // TODO else?
-                            }
+                Activity::Drinking(drinking_state) => match drinking_state {
+                    DrinkingState::Drinking => {
+                        inventory_senders
+                            .send(human.inventory_id, ItemMessage::Remove(Item::WATER, 1));
+                        human.thirst -= WATER_QUENCH;
+                    }
+                    DrinkingState::Finding => {
+                        if sent_mail {
+                            // wait for the water to arrive
+                            self.wait = 2;
                         }
-                        WorkState::Storing => {
-                            if let Some(target_inventory_id) = self.target_inventory_id {
-                                inventory_senders[human.inventory_id].send(ItemMessage::Transfer(
-                                    human.inventory_id,
-                                    Item::Food,
-                                    100,
-                                    target_inventory_id,
-                                )); // TODO is this the best way to give all?
-                                self.target_inventory_id = None;
-                            }
+                    }
+                },
+
+                Activity::Working(work_state) => match work_state {
+                    WorkState::Commuting => {
+                        if sent_mail {
+                            // wait for the tool they went for to arrive
+                            self.wait = 2;
                         }
-                    },
+                    }
+                    WorkState::Working => self.progress += 1,
+                    WorkState::Storing => (), // the plan sends what's put away
+                    WorkState::Selling => {
+                        if let Some(market_index) = self.target_market.take() {
+                            let _ = market_sender.send(MarketMessage::OpenStall(
+                                market_index,
+                                human.inventory_id,
+                                human.job.product(items),
+                                self.sell_quantity,
+                            ));
+                            self.sold_today = true;
+                            // store whatever wasn't surplus
+                            self.state = Activity::Working(WorkState::Storing);
+                            self.wait = 2;
+                        }
+                    }
                 },
 
-                Activity::Sleeping => {
+                Activity::Praying => {
                     if self.current_path.is_empty() {
-                        human.fatigue -= SLEEP_PER_TICK
+                        self.prayer_progress += 1;
                     }
                 }
+
+                Activity::Shopping => {
+                    self.report_purchase(human, market_sender);
+                    // the purchases go out with the mail; wait for them to come back
+                    if sent_mail {
+                        self.wait = 2;
+                    }
+                }
+
+                Activity::Sleeping => {
+                    human.fatigue = (human.fatigue - SLEEP_PER_TICK * self.sleep_quality).max(0.0)
+                }
+
+                Activity::Socialising | Activity::Wandering(_) => (), // let travel do the work
             }
         }
         human.fatigue += FATIGUE_PER_TICK;
+        human.thirst += THIRST_PER_TICK;
+        human.update_health();
 
+        // the young and old need less
+        let appetite = human.stage().food_share();
         if human.hunger < 80.0 {
-            human.hunger += 40.0 / (TICKS_PER_MINUTE as f32 * 60.0 * 24.0);
+            human.hunger += appetite * 40.0 / (TICKS_PER_MINUTE as f32 * 60.0 * 24.0);
         } else if human.hunger < 110.0 {
-            human.hunger += 30.0 / (TICKS_PER_MINUTE as f32 * 60.0 * 24.0);
+            human.hunger += appetite * 30.0 / (TICKS_PER_MINUTE as f32 * 60.0 * 24.0);
         } else if human.hunger < 130.0 {
-            human.hunger += 20.0 / (TICKS_PER_MINUTE as f32 * 60.0 * 24.0);
+            human.hunger += appetite * 20.0 / (TICKS_PER_MINUTE as f32 * 60.0 * 24.0);
         } else {
-            human.hunger += 10.0 / (TICKS_PER_MINUTE as f32 * 60.0 * 24.0);
+            human.hunger += appetite * 10.0 / (TICKS_PER_MINUTE as f32 * 60.0 * 24.0);
+        }
+    }
+
+    // lets the market know who bought from whom, if they just bought something
+    fn report_purchase(&mut self, human: &Human, market_sender: &Sender<MarketMessage>) {
+        if let (Some(market_index), Some(stall_inventory_id)) =
+            (self.target_market.take(), self.target_inventory_id.take())
+        {
+            let _ = market_sender.send(MarketMessage::Bought(
+                market_index,
+                stall_inventory_id,
+                human.inventory_id,
+            ));
         }
     }
 
     pub fn travel(&self, human: &mut Human, frames_per_tick: u8) {
         if let Some(travel_vector) = self.travel_vector {
-            human.location += travel_vector.with_len(human.speed / frames_per_tick as f32);
+            human.location += travel_vector.with_len(human.speed() / frames_per_tick as f32);
         }
     }
 }
+
+impl Save for Activity {
+    fn save(&self, writer: &mut SaveWriter) {
+        writer.put(match self {
+            Activity::Idle => "idle",
+            Activity::Sleeping => "sleeping",
+            Activity::Eating(EatingState::Eating) => "eating",
+            Activity::Eating(EatingState::Finding) => "finding_food",
+            Activity::Drinking(DrinkingState::Drinking) => "drinking",
+            Activity::Drinking(DrinkingState::Finding) => "finding_water",
+            Activity::Working(WorkState::Commuting) => "commuting",
+            Activity::Working(WorkState::Working) => "working",
+            Activity::Working(WorkState::Storing) => "storing",
+            Activity::Working(WorkState::Selling) => "selling",
+            Activity::Praying => "praying",
+            Activity::Shopping => "shopping",
+            Activity::Socialising => "socialising",
+            Activity::Wandering(WanderState::Starting) => "setting_off",
+            Activity::Wandering(WanderState::Walking) => "wandering",
+        });
+    }
+
+    fn load(reader: &mut SaveReader) -> Result<Activity, SaveError> {
+        let name: String = reader.get()?;
+        Ok(match name.as_str() {
+            "idle" => Activity::Idle,
+            "sleeping" => Activity::Sleeping,
+            "eating" => Activity::Eating(EatingState::Eating),
+            "finding_food" => Activity::Eating(EatingState::Finding),
+            "drinking" => Activity::Drinking(DrinkingState::Drinking),
+            "finding_water" => Activity::Drinking(DrinkingState::Finding),
+            "commuting" => Activity::Working(WorkState::Commuting),
+            "working" => Activity::Working(WorkState::Working),
+            "storing" => Activity::Working(WorkState::Storing),
+            "selling" => Activity::Working(WorkState::Selling),
+            "praying" => Activity::Praying,
+            "shopping" => Activity::Shopping,
+            "socialising" => Activity::Socialising,
+            "setting_off" => Activity::Wandering(WanderState::Starting),
+            "wandering" => Activity::Wandering(WanderState::Walking),
+            _ => return Err(SaveError::Format(format!("unknown activity '{}'", name))),
+        })
+    }
+}
+
+impl Save for Human {
+    fn save(&self, writer: &mut SaveWriter) {
+        writer.tag("human");
+        writer.put(&self.name);
+        writer.put_vector(self.location);
+        writer.put(self.inventory_id);
+        writer.put(self.fatigue);
+        writer.put(self.hunger);
+        writer.put(self.thirst);
+        writer.put(self.health);
+        writer.put(self.illness_days);
+        writer.put_option(self.last_harm);
+        writer.put(self.age);
+        writer.put(self.born);
+        self.biography.save(writer);
+        writer.put_option(self.partner);
+        writer.put(self.speed);
+        writer.put(self.household);
+        self.relationships.save(writer);
+        self.personality.save(writer);
+        self.job.save(writer);
+    }
+
+    fn load(reader: &mut SaveReader) -> Result<Human, SaveError> {
+        reader.expect("human")?;
+        let name = reader.get()?;
+        let location = reader.get_vector()?;
+        let inventory_id = reader.get()?;
+        let fatigue = reader.get()?;
+        let hunger = reader.get()?;
+        let thirst = reader.get()?;
+        let health = reader.get()?;
+        let illness_days = reader.get()?;
+        let last_harm = reader.get_option()?;
+        let age = reader.get()?;
+        let born = reader.get()?;
+        let biography = Biography::load(reader)?;
+        let partner = reader.get_option()?;
+        let speed = reader.get()?;
+        let household = reader.get()?;
+        let relationships = Relationships::load(reader)?;
+        let personality = Personality::load(reader)?;
+        let job = Job::load(reader)?;
+        Ok(Human {
+            name: name,
+            location: location,
+            inventory_id: inventory_id,
+            fatigue: fatigue,
+            hunger: hunger,
+            thirst: thirst,
+            health: health,
+            illness_days: illness_days,
+            last_harm: last_harm,
+            age: age,
+            born: born,
+            biography: biography,
+            partner: partner,
+            household: household,
+            relationships: relationships,
+            personality: personality,
+            speed: speed,
+            job: job,
+        })
+    }
+}
+
+impl Save for Mind {
+    fn save(&self, writer: &mut SaveWriter) {
+        writer.tag("mind");
+        self.state.save(writer);
+        writer.put(self.had_breakfast);
+        writer.put(self.had_dinner);
+        writer.put(self.meal_size);
+        writer.put_option(self.target_inventory_id);
+        writer.put_list("plan", &self.plan);
+        writer.put(self.replans);
+        writer.put(self.progress);
+        writer.put(self.wait);
+        writer.put_option_vector(self.travel_vector);
+        writer.put(self.prayed_today);
+        writer.put(self.days_since_prayer);
+        writer.put(self.prayer_progress);
+        writer.put_option(self.target_market);
+        writer.put(self.sell_quantity);
+        writer.put(self.sold_today);
+        writer.put(self.shopped_today);
+        writer.put(self.took_money);
+        writer.put_option(self.went_hungry);
+        writer.put(self.socialised_today);
+        writer.put(self.company);
+        writer.put(self.sleep_quality);
+        writer.put_list("path", &self.current_path);
+        writer.put_list("mail", &self.mail);
+        self.beliefs.save(writer);
+        self.knowledge.save(writer);
+    }
+
+    fn load(reader: &mut SaveReader) -> Result<Mind, SaveError> {
+        reader.expect("mind")?;
+        let state = Activity::load(reader)?;
+        let had_breakfast = reader.get()?;
+        let had_dinner = reader.get()?;
+        let meal_size = reader.get()?;
+        let target_inventory_id = reader.get_option()?;
+        let plan = reader.get_list("plan")?;
+        let replans = reader.get()?;
+        let progress = reader.get()?;
+        let wait = reader.get()?;
+        let travel_vector = reader.get_option_vector()?;
+        let prayed_today = reader.get()?;
+        let days_since_prayer = reader.get()?;
+        let prayer_progress = reader.get()?;
+        let target_market = reader.get_option()?;
+        let sell_quantity = reader.get()?;
+        let sold_today = reader.get()?;
+        let shopped_today = reader.get()?;
+        let took_money = reader.get()?;
+        let went_hungry = reader.get_option()?;
+        let socialised_today = reader.get()?;
+        let company = reader.get()?;
+        let sleep_quality = reader.get()?;
+        let current_path = reader.get_list("path")?;
+        let mail = reader.get_list("mail")?;
+        Ok(Mind {
+            current_path: current_path,
+            state: state,
+            had_breakfast: had_breakfast,
+            had_dinner: had_dinner,
+            meal_size: meal_size,
+            target_inventory_id: target_inventory_id,
+            plan: plan,
+            replans: replans,
+            progress: progress,
+            wait: wait,
+            travel_vector: travel_vector,
+
+            beliefs: Beliefs::load(reader)?,
+            knowledge: Knowledge::load(reader)?,
+            prayed_today: prayed_today,
+            days_since_prayer: days_since_prayer,
+            prayer_progress: prayer_progress,
+
+            target_market: target_market,
+            sell_quantity: sell_quantity,
+            mail: mail,
+            sold_today: sold_today,
+            shopped_today: shopped_today,
+            took_money: took_money,
+            went_hungry: went_hungry,
+            socialised_today: socialised_today,
+            company: company,
+            sleep_quality: sleep_quality,
+        })
+    }
+}
@@ -1,37 +1,129 @@
-use std::collections::HashMap;
-use std::sync::mpsc::{channel, Receiver, Sender};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+use std::sync::mpsc::Sender;
 
-#[derive(Copy, Clone, Eq, PartialEq, Hash)]
-pub enum Item {
-    Food,
-    Money,
-    Water,
-    // TODO Alcohol (crafted from Food)
-    // TODO Wood,
-    // TODO LuxuryGood (crafted from Wood),
-}
+use crate::catalogue::ItemCatalogue;
+use crate::entity::{Handle, HandleMap};
+use crate::save::{Save, SaveError, SaveReader, SaveWriter};
+
+/// A kind of item: an index into the world's `ItemCatalogue`, which knows its name, weight and
+/// so on. The items the simulation itself relies on have fixed indices.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, PartialOrd, Ord)]
+pub struct Item(u16);
+
+pub type InventoryId = Handle<Inventory>;
 
+/// Where to send mail for each inventory.
+pub type Senders = HandleMap<Inventory, Sender<ItemMessage>>;
+
+// Give and GiveOrDrop bring in goods that are new to the world, fresh today; the Aged versions
+// carry goods on from another inventory, as old as they were there.
+#[derive(Eq, PartialEq, PartialOrd, Ord)]
 pub enum ItemMessage {
-    Give(Item, u32, usize),
+    Give(Item, u32, InventoryId),
     GiveOrDrop(Item, u32),
-    Trade((Item, u32), (Item, u32), usize),
-    Take(Item, u32, usize),
+    GiveAged(Item, Vec<Stack>, InventoryId),
+    GiveAgedOrDrop(Item, Vec<Stack>),
+    Trade((Item, u32), (Item, u32), InventoryId),
+    Take(Item, u32, InventoryId),
     Remove(Item, u32),
-    Transfer(usize, Item, u32, usize),
+    Transfer(InventoryId, Item, u32, InventoryId),
+    Craft(Vec<(Item, u32)>, Vec<(Item, u32)>, InventoryId), // inputs used up here, outputs sent on
+}
+
+/// A message together with the inventory it's for, decided on in `think` and sent in `act`.
+pub struct Mail {
+    pub inventory_id: InventoryId,
+    pub message: ItemMessage,
 }
 
 pub struct Inventory {
     // id: u32,
-    items: HashMap<Item, u32>,
+    items: BTreeMap<Item, Vec<Stack>>, // ordered so weight sums and iteration are deterministic
     capacity: f32,
+    spoilage: f32, // days things age here per day that passes; good storage keeps this low
+}
+
+/// Some of an item that arrived on the same day, and has been ageing together since. Stacks
+/// keep their age when they're moved, so nothing gets any fresher for being passed around.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Stack {
+    pub quantity: u32,
+    pub age: f32, // days
 }
 
 impl Item {
-    fn weight(&self) -> f32 {
-        match self {
-            Item::Food => 1.0,
-            Item::Money => 0.1,
-            Item::Water => 1.0,
+    pub const FOOD: Item = Item(0);
+    pub const MONEY: Item = Item(1);
+    pub const WATER: Item = Item(2);
+    pub const WOOD: Item = Item(3);
+    pub const ALCOHOL: Item = Item(4); // brewed from food
+    pub const LUXURY_GOOD: Item = Item(5); // crafted from wood
+    pub const WASTE: Item = Item(6); // what perishables turn into
+
+    pub fn from_index(index: usize) -> Item {
+        Item(index as u16)
+    }
+
+    pub fn index(&self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl fmt::Display for Item {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+// saves refer to items by catalogue index, so loading doesn't need the catalogue at hand
+impl FromStr for Item {
+    type Err = ();
+
+    fn from_str(index: &str) -> Result<Item, ()> {
+        index.parse().map(Item).map_err(|_| ())
+    }
+}
+
+// ages are never NaN, since nothing ages at a NaN rate
+impl Eq for Stack {}
+
+impl PartialOrd for Stack {
+    fn partial_cmp(&self, other: &Stack) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Stack {
+    fn cmp(&self, other: &Stack) -> Ordering {
+        self.quantity
+            .cmp(&other.quantity)
+            .then(self.age.to_bits().cmp(&other.age.to_bits()))
+    }
+}
+
+impl Hash for Inventory {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for (item, stacks) in self.items.iter() {
+            item.hash(state);
+            for stack in stacks {
+                stack.quantity.hash(state);
+                stack.age.to_bits().hash(state);
+            }
+        }
+        self.capacity.to_bits().hash(state);
+        self.spoilage.to_bits().hash(state);
+    }
+}
+
+impl Senders {
+    /// Mail for an inventory that no longer exists is dropped, as is anything it was carrying.
+    pub fn send(&self, inventory_id: InventoryId, message: ItemMessage) {
+        if let Some(sender) = self.get(inventory_id) {
+            let _ = sender.send(message);
         }
     }
 }
@@ -40,21 +132,38 @@ impl Inventory {
     pub fn new(capacity: f32) -> Inventory {
         Inventory {
             // id: id,
-            items: HashMap::new(),
+            items: BTreeMap::new(),
             capacity: capacity,
+            spoilage: 1.0,
         }
     }
 
-    pub fn description_lines(&self) -> Vec<String> {
+    /// Makes things kept here age `spoilage` days per day, rather than one.
+    pub fn set_spoilage(&mut self, spoilage: f32) {
+        self.spoilage = spoilage;
+    }
+
+    pub fn description_lines(&self, items: &ItemCatalogue) -> Vec<String> {
         let mut lines = vec!["Inventory".to_string()];
-        lines.push(format!("Capacity: {:.2}/{:.2}", self.weight(), self.capacity));
-        for (item, count) in self.items.iter() {
-            if *count > 0 {
-                lines.push(format!("{}: {}", match item {
-                    Item::Food => "Food",
-                    Item::Money => "Money",
-                    Item::Water => "Water",
-                }, count))
+        lines.push(format!(
+            "Capacity: {:.2}/{:.2}",
+            self.weight(items),
+            self.capacity
+        ));
+        if self.spoilage != 1.0 {
+            lines.push(format!("Spoilage: {:.2}x", self.spoilage));
+        }
+        for (item, stacks) in self.items.iter() {
+            let count = self.count(*item);
+            match (items.get(*item).spoils, stacks.first()) {
+                (Some(days), Some(oldest)) => lines.push(format!(
+                    "{}: {} (oldest {:.1}/{} days)",
+                    items.name(*item),
+                    count,
+                    oldest.age,
+                    days
+                )),
+                _ => lines.push(format!("{}: {}", items.name(*item), count)),
             }
         }
         lines
@@ -62,121 +171,614 @@ impl Inventory {
 
     pub fn receive_all(
         &mut self,
-        receiver: &Receiver<ItemMessage>,
-        senders: &Vec<Sender<ItemMessage>>,
+        messages: &mut Vec<ItemMessage>,
+        items: &ItemCatalogue,
+        senders: &Senders,
     ) {
-        while let Ok(msg) = receiver.try_recv() {
-            self.process_msg(msg, senders);
+        // arrival order depends on how rayon scheduled the senders, so process in a canonical
+        // order instead. Senders are deterministic, so the set of messages is too.
+        messages.sort();
+        for msg in messages.drain(..) {
+            self.process_msg(msg, items, senders);
         }
     }
 
-    fn process_msg(&mut self, msg: ItemMessage, senders: &Vec<Sender<ItemMessage>>) {
+    fn process_msg(&mut self, msg: ItemMessage, items: &ItemCatalogue, senders: &Senders) {
         match msg {
             ItemMessage::Give(received_item, received_quantity, ack_sender_id) => {
-                let given_quantity = self.do_give_up_to(received_item, received_quantity);
+                let given_quantity = self.do_give_up_to(received_item, received_quantity, items);
                 let ungiven = received_quantity - given_quantity;
                 if ungiven > 0 {
-                    let _ = senders[ack_sender_id]
-                        .send(ItemMessage::GiveOrDrop(received_item, ungiven));
+                    senders.send(
+                        ack_sender_id,
+                        ItemMessage::GiveOrDrop(received_item, ungiven),
+                    );
                 }
             }
             ItemMessage::GiveOrDrop(received_item, received_quantity) => {
-                self.do_give_up_to(received_item, received_quantity);
+                self.do_give_up_to(received_item, received_quantity, items);
+            }
+            ItemMessage::GiveAged(received_item, received_stacks, ack_sender_id) => {
+                let ungiven = self.do_give_stacks(received_item, received_stacks, items);
+                if !ungiven.is_empty() {
+                    senders.send(
+                        ack_sender_id,
+                        ItemMessage::GiveAgedOrDrop(received_item, ungiven),
+                    );
+                }
+            }
+            ItemMessage::GiveAgedOrDrop(received_item, received_stacks) => {
+                self.do_give_stacks(received_item, received_stacks, items);
             }
             ItemMessage::Trade(
                 (received_item, received_quantity),
                 (requested_item, requested_quantity),
                 ack_sender_id,
             ) => {
-                if self.do_take_exact(requested_item, requested_quantity) {
-                    if self.do_give_exact(received_item, received_quantity) {
-                        let _ = senders[ack_sender_id]
-                            .send(ItemMessage::GiveOrDrop(requested_item, requested_quantity));
+                if self.count(requested_item) >= requested_quantity {
+                    let requested_stacks = self.take_stacks(requested_item, requested_quantity);
+                    if self.do_give_exact(received_item, received_quantity, items) {
+                        senders.send(
+                            ack_sender_id,
+                            ItemMessage::GiveAgedOrDrop(requested_item, requested_stacks),
+                        );
                     } else {
-                        self.do_give_up_to(requested_item, requested_quantity);
-                        let _ = senders[ack_sender_id]
-                            .send(ItemMessage::GiveOrDrop(received_item, received_quantity));
+                        for stack in requested_stacks {
+                            self.add_stack(requested_item, stack);
+                        }
+                        senders.send(
+                            ack_sender_id,
+                            ItemMessage::GiveOrDrop(received_item, received_quantity),
+                        );
                     }
                 } else {
-                    let _ = senders[ack_sender_id]
-                        .send(ItemMessage::GiveOrDrop(received_item, received_quantity));
+                    senders.send(
+                        ack_sender_id,
+                        ItemMessage::GiveOrDrop(received_item, received_quantity),
+                    );
                 }
             }
             ItemMessage::Take(taken_item, taken_quantity, ack_sender_id) => {
-                let taken_quantity = self.do_take_up_to(taken_item, taken_quantity);
-                let _ = senders[ack_sender_id]
-                    .send(ItemMessage::GiveOrDrop(taken_item, taken_quantity));
+                // one stack is as much as can be carried off in one go
+                let taken_quantity = taken_quantity.min(items.get(taken_item).stack);
+                let taken_stacks = self.take_stacks(taken_item, taken_quantity);
+                senders.send(
+                    ack_sender_id,
+                    ItemMessage::GiveAgedOrDrop(taken_item, taken_stacks),
+                );
             }
             ItemMessage::Remove(taken_item, taken_quantity) => {
                 let _ = self.do_take_up_to(taken_item, taken_quantity);
             }
             ItemMessage::Transfer(this_sender_id, item, quantity, other_sender_id) => {
-                let quantity = self.do_take_up_to(item, quantity);
-                let _ = senders[other_sender_id].send(ItemMessage::Give(
-                    item,
-                    quantity,
-                    this_sender_id,
-                ));
+                let stacks = self.take_stacks(item, quantity);
+                senders.send(
+                    other_sender_id,
+                    ItemMessage::GiveAged(item, stacks, this_sender_id),
+                );
+            }
+            ItemMessage::Craft(inputs, outputs, ack_sender_id) => {
+                // all or nothing: short of any input, nothing gets made
+                if inputs
+                    .iter()
+                    .all(|&(item, quantity)| self.count(item) >= quantity)
+                {
+                    for &(item, quantity) in inputs.iter() {
+                        self.do_take_exact(item, quantity);
+                    }
+                    for (item, quantity) in outputs {
+                        senders.send(ack_sender_id, ItemMessage::GiveOrDrop(item, quantity));
+                    }
+                }
             }
         }
     }
 
-    fn weight(&self) -> f32 {
-        self.items
-            .iter()
-            .map(|(item, count)| item.weight() * (*count as f32))
-            .sum()
+    fn weight(&self, items: &ItemCatalogue) -> f32 {
+        self.items.keys().fold(0.0, |weight, &item| {
+            weight + items.weight(item) * self.count(item) as f32
+        })
     }
 
-    pub fn do_give_exact(&mut self, received_item: Item, received_quantity: u32) -> bool {
-        let current_weight = self.weight();
-        let added_weight = received_item.weight() + received_quantity as f32;
-        if current_weight + added_weight >= self.capacity {
-            if let Some(existing_quantity) = self.items.get_mut(&received_item) {
-                *existing_quantity += received_quantity;
-            } else {
-                self.items.insert(received_item, received_quantity);
-            }
+    pub fn do_give_exact(
+        &mut self,
+        received_item: Item,
+        received_quantity: u32,
+        items: &ItemCatalogue,
+    ) -> bool {
+        let current_weight = self.weight(items);
+        let added_weight = items.weight(received_item) * received_quantity as f32;
+        if current_weight + added_weight <= self.capacity {
+            self.add(received_item, received_quantity);
             true
         } else {
             false
         }
     }
 
-    pub fn do_give_up_to(&mut self, received_item: Item, received_quantity: u32) -> u32 {
-        let current_weight = self.weight();
+    pub fn do_give_up_to(
+        &mut self,
+        received_item: Item,
+        received_quantity: u32,
+        items: &ItemCatalogue,
+    ) -> u32 {
+        let current_weight = self.weight(items);
         let remaining_weight = self.capacity - current_weight;
-        let remaining_quantity = (remaining_weight / received_item.weight()).floor() as u32;
+        let remaining_quantity = (remaining_weight / items.weight(received_item)).floor() as u32;
         let given_quantity = remaining_quantity.min(received_quantity);
-        if let Some(existing_quantity) = self.items.get_mut(&received_item) {
-            *existing_quantity += given_quantity;
-        } else {
-            self.items.insert(received_item, given_quantity);
-        }
+        self.add(received_item, given_quantity);
         given_quantity
     }
 
-    pub fn do_take_exact(&mut self, taken_item: Item, taken_quantity: u32) -> bool {
-        if let Some(existing_quantity) = self.items.get_mut(&taken_item) {
-            if *existing_quantity >= taken_quantity {
-                *existing_quantity -= taken_quantity;
-                return true;
+    // as many of the stacks as fit, oldest first; returns whatever didn't
+    fn do_give_stacks(
+        &mut self,
+        received_item: Item,
+        received_stacks: Vec<Stack>,
+        items: &ItemCatalogue,
+    ) -> Vec<Stack> {
+        let mut room = self.room_for(received_item, items);
+        let mut ungiven = Vec::new();
+        for mut stack in received_stacks {
+            let given = stack.quantity.min(room);
+            room -= given;
+            if given > 0 {
+                self.add_stack(
+                    received_item,
+                    Stack {
+                        quantity: given,
+                        age: stack.age,
+                    },
+                );
+            }
+            stack.quantity -= given;
+            if stack.quantity > 0 {
+                ungiven.push(stack);
             }
         }
-        false
+        ungiven
     }
 
-    pub fn do_take_up_to(&mut self, taken_item: Item, taken_quantity: u32) -> u32 {
-        if let Some(existing_quantity) = self.items.get_mut(&taken_item) {
-            let taken_quantity = taken_quantity.min(*existing_quantity);
-            *existing_quantity -= taken_quantity;
-            taken_quantity
+    pub fn do_take_exact(&mut self, taken_item: Item, taken_quantity: u32) -> bool {
+        if self.count(taken_item) >= taken_quantity {
+            self.remove(taken_item, taken_quantity);
+            true
         } else {
-            0
+            false
         }
     }
 
+    pub fn do_take_up_to(&mut self, taken_item: Item, taken_quantity: u32) -> u32 {
+        self.remove(taken_item, taken_quantity)
+    }
+
+    // new today, so in with whatever else arrived fresh
+    fn add(&mut self, item: Item, quantity: u32) {
+        self.add_stack(
+            item,
+            Stack {
+                quantity: quantity,
+                age: 0.0,
+            },
+        );
+    }
+
+    // in with anything just as old, keeping the stacks oldest first
+    fn add_stack(&mut self, item: Item, stack: Stack) {
+        if stack.quantity == 0 {
+            return;
+        }
+        let stacks = self.items.entry(item).or_insert_with(Vec::new);
+        match stacks.iter().position(|other| other.age <= stack.age) {
+            Some(index) if stacks[index].age == stack.age => {
+                stacks[index].quantity += stack.quantity
+            }
+            Some(index) => stacks.insert(index, stack),
+            None => stacks.push(stack),
+        }
+    }
+
+    // oldest first, up to `quantity`; returns how many were removed
+    fn remove(&mut self, item: Item, quantity: u32) -> u32 {
+        self.take_stacks(item, quantity)
+            .iter()
+            .map(|stack| stack.quantity)
+            .sum()
+    }
+
+    // oldest first, up to `quantity`, as they were kept here
+    fn take_stacks(&mut self, item: Item, quantity: u32) -> Vec<Stack> {
+        let mut taken = Vec::new();
+        let mut removed = 0;
+        if let Some(stacks) = self.items.get_mut(&item) {
+            while removed < quantity && !stacks.is_empty() {
+                let quantity = stacks[0].quantity.min(quantity - removed);
+                stacks[0].quantity -= quantity;
+                removed += quantity;
+                taken.push(Stack {
+                    quantity: quantity,
+                    age: stacks[0].age,
+                });
+                if stacks[0].quantity == 0 {
+                    stacks.remove(0);
+                }
+            }
+            if stacks.is_empty() {
+                self.items.remove(&item);
+            }
+        }
+        taken
+    }
+
+    /// Called once a day: ages everything perishable, and turns whatever has been kept past
+    /// the days it keeps for into waste.
+    pub fn spoil(&mut self, items: &ItemCatalogue) {
+        let spoilage = self.spoilage;
+        let mut wasted = 0;
+        for (item, stacks) in self.items.iter_mut() {
+            if let Some(days) = items.get(*item).spoils {
+                for stack in stacks.iter_mut() {
+                    stack.age += spoilage;
+                }
+                // oldest first, so anything spoiled is at the front
+                while stacks
+                    .first()
+                    .map_or(false, |stack| stack.age >= days as f32)
+                {
+                    wasted += stacks.remove(0).quantity;
+                }
+            }
+        }
+        self.items.retain(|_, stacks| !stacks.is_empty());
+        self.add(Item::WASTE, wasted);
+    }
+
+    /// How many more of `item` would fit.
+    pub fn room_for(&self, item: Item, items: &ItemCatalogue) -> u32 {
+        ((self.capacity - self.weight(items)) / items.weight(item))
+            .max(0.0)
+            .floor() as u32
+    }
+
     pub fn count(&self, item: Item) -> u32 {
-        *self.items.get(&item).unwrap_or(&0)
+        self.items
+            .get(&item)
+            .map_or(0, |stacks| stacks.iter().map(|stack| stack.quantity).sum())
+    }
+}
+
+impl Save for ItemMessage {
+    fn save(&self, writer: &mut SaveWriter) {
+        match self {
+            ItemMessage::Give(item, quantity, ack_sender_id) => {
+                writer.tag("give");
+                writer.put(item);
+                writer.put(quantity);
+                writer.put(ack_sender_id);
+            }
+            ItemMessage::GiveOrDrop(item, quantity) => {
+                writer.tag("give_or_drop");
+                writer.put(item);
+                writer.put(quantity);
+            }
+            ItemMessage::GiveAged(item, stacks, ack_sender_id) => {
+                writer.tag("give_aged");
+                writer.put(item);
+                put_stacks(writer, stacks);
+                writer.put(ack_sender_id);
+            }
+            ItemMessage::GiveAgedOrDrop(item, stacks) => {
+                writer.tag("give_aged_or_drop");
+                writer.put(item);
+                put_stacks(writer, stacks);
+            }
+            ItemMessage::Trade(
+                (received, received_quantity),
+                (requested, requested_quantity),
+                ack_sender_id,
+            ) => {
+                writer.tag("trade");
+                writer.put(received);
+                writer.put(received_quantity);
+                writer.put(requested);
+                writer.put(requested_quantity);
+                writer.put(ack_sender_id);
+            }
+            ItemMessage::Take(item, quantity, ack_sender_id) => {
+                writer.tag("take");
+                writer.put(item);
+                writer.put(quantity);
+                writer.put(ack_sender_id);
+            }
+            ItemMessage::Remove(item, quantity) => {
+                writer.tag("remove");
+                writer.put(item);
+                writer.put(quantity);
+            }
+            ItemMessage::Transfer(this_sender_id, item, quantity, other_sender_id) => {
+                writer.tag("transfer");
+                writer.put(this_sender_id);
+                writer.put(item);
+                writer.put(quantity);
+                writer.put(other_sender_id);
+            }
+            ItemMessage::Craft(inputs, outputs, ack_sender_id) => {
+                writer.tag("craft");
+                put_items(writer, inputs);
+                put_items(writer, outputs);
+                writer.put(ack_sender_id);
+            }
+        }
+    }
+
+    fn load(reader: &mut SaveReader) -> Result<ItemMessage, SaveError> {
+        let kind: String = reader.get()?;
+        Ok(match kind.as_str() {
+            "give" => ItemMessage::Give(reader.get()?, reader.get()?, reader.get()?),
+            "give_or_drop" => ItemMessage::GiveOrDrop(reader.get()?, reader.get()?),
+            "give_aged" => ItemMessage::GiveAged(reader.get()?, get_stacks(reader)?, reader.get()?),
+            "give_aged_or_drop" => ItemMessage::GiveAgedOrDrop(reader.get()?, get_stacks(reader)?),
+            "trade" => ItemMessage::Trade(
+                (reader.get()?, reader.get()?),
+                (reader.get()?, reader.get()?),
+                reader.get()?,
+            ),
+            "take" => ItemMessage::Take(reader.get()?, reader.get()?, reader.get()?),
+            "remove" => ItemMessage::Remove(reader.get()?, reader.get()?),
+            "transfer" => {
+                ItemMessage::Transfer(reader.get()?, reader.get()?, reader.get()?, reader.get()?)
+            }
+            "craft" => ItemMessage::Craft(get_items(reader)?, get_items(reader)?, reader.get()?),
+            _ => {
+                return Err(SaveError::Format(format!(
+                    "unknown item message '{}'",
+                    kind
+                )))
+            }
+        })
+    }
+}
+
+fn put_items(writer: &mut SaveWriter, items: &[(Item, u32)]) {
+    writer.put(items.len());
+    for (item, quantity) in items {
+        writer.put(item);
+        writer.put(quantity);
+    }
+}
+
+fn get_items(reader: &mut SaveReader) -> Result<Vec<(Item, u32)>, SaveError> {
+    let len: usize = reader.get()?;
+    let mut items = Vec::new();
+    for _ in 0..len {
+        items.push((reader.get()?, reader.get()?));
+    }
+    Ok(items)
+}
+
+fn put_stacks(writer: &mut SaveWriter, stacks: &[Stack]) {
+    writer.put(stacks.len());
+    for stack in stacks {
+        writer.put(stack.quantity);
+        writer.put(stack.age);
+    }
+}
+
+fn get_stacks(reader: &mut SaveReader) -> Result<Vec<Stack>, SaveError> {
+    let len: usize = reader.get()?;
+    let mut stacks = Vec::new();
+    for _ in 0..len {
+        stacks.push(Stack {
+            quantity: reader.get()?,
+            age: reader.get()?,
+        });
+    }
+    Ok(stacks)
+}
+
+impl Mail {
+    pub fn new(inventory_id: InventoryId, message: ItemMessage) -> Mail {
+        Mail {
+            inventory_id: inventory_id,
+            message: message,
+        }
+    }
+}
+
+impl Save for Mail {
+    fn save(&self, writer: &mut SaveWriter) {
+        writer.tag("mail");
+        writer.put(self.inventory_id);
+        self.message.save(writer);
+    }
+
+    fn load(reader: &mut SaveReader) -> Result<Mail, SaveError> {
+        reader.expect("mail")?;
+        Ok(Mail {
+            inventory_id: reader.get()?,
+            message: ItemMessage::load(reader)?,
+        })
+    }
+}
+
+impl Save for Inventory {
+    fn save(&self, writer: &mut SaveWriter) {
+        writer.tag("inventory");
+        writer.put(self.capacity);
+        writer.put(self.spoilage);
+        writer.put(self.items.len());
+        for (item, stacks) in self.items.iter() {
+            writer.put(item);
+            writer.put(stacks.len());
+            for stack in stacks {
+                writer.put(stack.quantity);
+                writer.put(stack.age);
+            }
+        }
+    }
+
+    fn load(reader: &mut SaveReader) -> Result<Inventory, SaveError> {
+        reader.expect("inventory")?;
+        let mut inventory = Inventory::new(reader.get()?);
+        inventory.spoilage = reader.get()?;
+        let len: usize = reader.get()?;
+        for _ in 0..len {
+            let item = reader.get()?;
+            let stack_count: usize = reader.get()?;
+            let mut stacks = Vec::new();
+            for _ in 0..stack_count {
+                stacks.push(Stack {
+                    quantity: reader.get()?,
+                    age: reader.get()?,
+                });
+            }
+            inventory.items.insert(item, stacks);
+        }
+        Ok(inventory)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::Arena;
+    use std::sync::mpsc::{channel, Receiver};
+
+    struct Post {
+        inventories: Arena<Inventory>,
+        senders: Senders,
+        receivers: HandleMap<Inventory, Receiver<ItemMessage>>,
+    }
+
+    impl Post {
+        fn new() -> Post {
+            Post {
+                inventories: Arena::new(),
+                senders: HandleMap::new(),
+                receivers: HandleMap::new(),
+            }
+        }
+
+        fn add(&mut self, inventory: Inventory) -> InventoryId {
+            let inventory_id = self.inventories.insert(inventory);
+            let (send, recv) = channel();
+            self.senders.insert(inventory_id, send);
+            self.receivers.insert(inventory_id, recv);
+            inventory_id
+        }
+
+        // delivers mail until there's none left, replies included
+        fn deliver(&mut self, items: &ItemCatalogue) {
+            loop {
+                let mut delivered = false;
+                for (inventory_id, recv) in self.receivers.iter() {
+                    let mut messages: Vec<ItemMessage> = recv.try_iter().collect();
+                    delivered = delivered || !messages.is_empty();
+                    self.inventories[inventory_id].receive_all(&mut messages, items, &self.senders);
+                }
+                if !delivered {
+                    return;
+                }
+            }
+        }
+    }
+
+    fn catalogue() -> ItemCatalogue {
+        ItemCatalogue::parse(include_str!("../static/data/items.catalogue")).unwrap()
+    }
+
+    fn stack(quantity: u32, age: f32) -> Stack {
+        Stack {
+            quantity: quantity,
+            age: age,
+        }
+    }
+
+    #[test]
+    fn stacks_kept_oldest_first() {
+        let items = catalogue();
+        let mut inventory = Inventory::new(100.0);
+        inventory.do_give_up_to(Item::FOOD, 5, &items);
+        inventory.spoil(&items);
+        inventory.do_give_up_to(Item::FOOD, 3, &items);
+        inventory.do_give_stacks(Item::FOOD, vec![stack(2, 1.0), stack(4, 3.0)], &items);
+        inventory.do_give_stacks(Item::FOOD, vec![stack(1, 0.5)], &items);
+        assert_eq!(
+            inventory.items[&Item::FOOD],
+            vec![stack(4, 3.0), stack(7, 1.0), stack(1, 0.5), stack(3, 0.0)]
+        );
+        assert_eq!(
+            inventory.take_stacks(Item::FOOD, 6),
+            vec![stack(4, 3.0), stack(2, 1.0)]
+        );
+    }
+
+    #[test]
+    fn age_survives_moves() {
+        let items = catalogue();
+        let mut post = Post::new();
+        let store = post.add(Inventory::new(100.0));
+        let shelf = post.add(Inventory::new(100.0));
+        let human = post.add(Inventory::new(100.0));
+        post.inventories[store].do_give_up_to(Item::FOOD, 10, &items);
+        post.inventories[store].spoil(&items);
+        post.inventories[store].spoil(&items);
+        post.inventories[human].do_give_up_to(Item::MONEY, 10, &items);
+
+        post.senders
+            .send(store, ItemMessage::Transfer(store, Item::FOOD, 4, shelf));
+        post.deliver(&items);
+        assert_eq!(
+            post.inventories[shelf].items[&Item::FOOD],
+            vec![stack(4, 2.0)]
+        );
+
+        post.senders
+            .send(store, ItemMessage::Take(Item::FOOD, 3, human));
+        post.senders.send(
+            shelf,
+            ItemMessage::Trade((Item::MONEY, 2), (Item::FOOD, 2), human),
+        );
+        post.inventories[human].do_take_exact(Item::MONEY, 2); // paid out up front
+        post.deliver(&items);
+        assert_eq!(
+            post.inventories[human].items[&Item::FOOD],
+            vec![stack(5, 2.0)]
+        );
+        assert_eq!(post.inventories[shelf].count(Item::MONEY), 2);
+    }
+
+    #[test]
+    fn storage_slows_ageing() {
+        let items = catalogue();
+        let mut cellar = Inventory::new(100.0);
+        cellar.set_spoilage(0.5);
+        let mut floor = Inventory::new(100.0);
+        for inventory in [&mut cellar, &mut floor].iter_mut() {
+            inventory.do_give_up_to(Item::FOOD, 5, &items);
+            for _ in 0..3 {
+                inventory.spoil(&items);
+            }
+        }
+        assert_eq!(cellar.items[&Item::FOOD], vec![stack(5, 1.5)]);
+        assert_eq!(floor.items[&Item::FOOD], vec![stack(5, 3.0)]);
+    }
+
+    #[test]
+    fn waste_at_spoils_limit() {
+        let items = catalogue();
+        let days = items.get(Item::FOOD).spoils.unwrap();
+        let mut inventory = Inventory::new(100.0);
+        inventory.do_give_up_to(Item::FOOD, 5, &items);
+        for _ in 1..days {
+            inventory.spoil(&items);
+        }
+        inventory.do_give_up_to(Item::FOOD, 2, &items);
+        assert_eq!(inventory.count(Item::FOOD), 7);
+        assert_eq!(inventory.count(Item::WASTE), 0);
+
+        inventory.spoil(&items);
+        assert_eq!(inventory.items[&Item::FOOD], vec![stack(2, 1.0)]);
+        assert_eq!(inventory.count(Item::WASTE), 5);
     }
 }
@@ -0,0 +1,309 @@
+use quicksilver::geom::Vector;
+
+use crate::catalogue::ItemCatalogue;
+use crate::geography::TilePoint;
+use crate::human::Human;
+use crate::item::{InventoryId, Item, ItemMessage, Mail};
+use crate::market;
+use crate::plant::CropId;
+use crate::save::{Save, SaveError, SaveReader, SaveWriter};
+use crate::world::World;
+
+const HARVEST: u32 = 50;
+const WOOD_PER_DAY: u32 = 20;
+const MERCHANT_MARGIN: f32 = 0.8; // only buy at this fraction of the average price
+
+/// What someone does for a living. Every job follows the same daily cycle in `Mind`: commute
+/// to the workplace, work there for the day, then store (or sell) what they made. Only the
+/// details of each step live here, so adding a profession doesn't touch `human.rs`.
+#[derive(Clone, Hash)]
+pub enum Job {
+    Farmer(CropId),
+    Woodcutter(TilePoint), // where the trees are
+    Artisan(usize, usize), // workshop index, recipe index; what they're called comes from the recipe
+    Merchant(Item),        // what they deal in, at the nearest market
+}
+
+impl Job {
+    /// Fails if the crop, workshop or recipe it names isn't in `world`.
+    pub fn check_references(&self, world: &World) -> Result<(), SaveError> {
+        match self {
+            Job::Farmer(crop_id) if !world.crops.contains(*crop_id) => {
+                Err(SaveError::dangling("crop", crop_id))
+            }
+            Job::Artisan(workshop_index, _) if *workshop_index >= world.workshops.len() => {
+                Err(SaveError::dangling("workshop", workshop_index))
+            }
+            Job::Artisan(_, recipe_index) if *recipe_index >= world.items.recipe_count() => {
+                Err(SaveError::dangling("recipe", recipe_index))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn name<'a>(&self, world: &'a World) -> &'a str {
+        match self {
+            Job::Farmer(_) => "Farmer",
+            Job::Woodcutter(_) => "Woodcutter",
+            Job::Artisan(_, recipe_index) => &world.items.recipe(*recipe_index).worker,
+            Job::Merchant(_) => "Merchant",
+        }
+    }
+
+    /// What a day's work yields, or the main thing if it yields several.
+    pub fn product(&self, items: &ItemCatalogue) -> Item {
+        match self {
+            Job::Farmer(_) => Item::FOOD,
+            Job::Woodcutter(_) => Item::WOOD,
+            Job::Artisan(_, recipe_index) => items.recipe(*recipe_index).outputs[0].0,
+            Job::Merchant(item) => *item,
+        }
+    }
+
+    /// What the work uses up, if anything.
+    pub fn inputs(&self, items: &ItemCatalogue) -> Vec<Item> {
+        match self {
+            Job::Artisan(_, recipe_index) => items
+                .recipe(*recipe_index)
+                .inputs
+                .iter()
+                .map(|&(item, _)| item)
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// What they need to hand to work, if anything.
+    pub fn tool(&self, items: &ItemCatalogue) -> Option<Item> {
+        match self {
+            Job::Artisan(_, recipe_index) => items.recipe(*recipe_index).tool,
+            _ => None,
+        }
+    }
+
+    /// Everything the work makes, uses up or needs to hand, which is kept in stores between
+    /// working days.
+    pub fn work_items(&self, items: &ItemCatalogue) -> Vec<Item> {
+        let mut work_items = match self {
+            Job::Artisan(_, recipe_index) => items
+                .recipe(*recipe_index)
+                .outputs
+                .iter()
+                .map(|&(item, _)| item)
+                .collect(),
+            _ => vec![self.product(items)],
+        };
+        work_items.extend(self.inputs(items));
+        work_items.extend(self.tool(items));
+        work_items
+    }
+
+    /// How much likelier than usual this work makes an accident.
+    pub fn hazard(&self) -> f32 {
+        match self {
+            Job::Farmer(_) | Job::Artisan(_, _) => 1.0,
+            Job::Woodcutter(_) => 3.0, // axes and falling trees
+            Job::Merchant(_) => 0.5,
+        }
+    }
+
+    /// Whether they need all their money on them for work, rather than putting what they can
+    /// spare by at home.
+    pub fn keeps_money(&self) -> bool {
+        match self {
+            Job::Merchant(_) => true, // it's what they trade with
+            _ => false,
+        }
+    }
+
+    pub fn works_crop(&self, crop_id: CropId) -> bool {
+        match self {
+            Job::Farmer(farmed_crop_id) => *farmed_crop_id == crop_id,
+            _ => false,
+        }
+    }
+
+    /// Whether someone doing `other` works alongside them: on the same crop, in the same woods
+    /// or at the same workshop. Merchants are rivals rather than colleagues.
+    pub fn works_with(&self, other: &Job) -> bool {
+        match (self, other) {
+            (Job::Farmer(a), Job::Farmer(b)) => a == b,
+            (Job::Woodcutter(a), Job::Woodcutter(b)) => a == b,
+            (Job::Artisan(a, _), Job::Artisan(b, _)) => a == b,
+            _ => false,
+        }
+    }
+
+    /// Where the day's work is done, if there's anywhere to do it. A farmer whose crop is gone
+    /// has nowhere to work.
+    pub fn workplace(&self, human: &Human, world: &World) -> Option<Vector> {
+        match self {
+            Job::Farmer(crop_id) => world.crops.get(*crop_id).map(|crop| crop.location),
+            Job::Woodcutter(trees) => Some(Vector::new(trees.x as f32 + 0.5, trees.y as f32 + 0.5)),
+            Job::Artisan(workshop_index, _) => Some(world.workshops[*workshop_index].location),
+            Job::Merchant(_) => world
+                .nearest_market(human.location)
+                .map(|market_index| world.markets[market_index].location),
+        }
+    }
+
+    /// The inventory worked on at the workplace.
+    pub fn work_inventory(&self, world: &World) -> Option<InventoryId> {
+        match self {
+            Job::Farmer(crop_id) => world.crops.get(*crop_id).map(|crop| crop.inventory_id),
+            Job::Artisan(workshop_index, _) => Some(world.workshops[*workshop_index].inventory_id),
+            Job::Woodcutter(_) | Job::Merchant(_) => None,
+        }
+    }
+
+    /// Mail sent on arriving at work: dropping off any materials brought along.
+    pub fn start_work(&self, human: &Human, world: &World) -> Vec<Mail> {
+        match (self, self.work_inventory(world)) {
+            (Job::Artisan(_, _), Some(work_inventory_id)) => self
+                .inputs(&world.items)
+                .into_iter()
+                .filter_map(|item| {
+                    let carried = world.inventories[human.inventory_id].count(item);
+                    if carried > 0 {
+                        Some(Mail::new(
+                            human.inventory_id,
+                            ItemMessage::Transfer(
+                                human.inventory_id,
+                                item,
+                                carried,
+                                work_inventory_id,
+                            ),
+                        ))
+                    } else {
+                        None
+                    }
+                })
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Mail sent every tick spent working.
+    pub fn work(&self, human: &Human, world: &World) -> Vec<Mail> {
+        match self {
+            Job::Merchant(item) => {
+                // buy up anything going cheap, to sell on when the price recovers
+                let market = match world.nearest_market(human.location) {
+                    Some(market_index) => &world.markets[market_index],
+                    None => return Vec::new(),
+                };
+                let money = world.inventories[human.inventory_id].count(Item::MONEY);
+                let bargain = market.average_price(*item) * MERCHANT_MARGIN;
+                match market.cheapest(*item, &world.inventories) {
+                    Some((stall_inventory_id, price, stock)) if price < bargain => {
+                        let quantity = stock.min((money as f32 / price).floor() as u32);
+                        if quantity > 0 {
+                            let cost = ((quantity as f32 * price).ceil() as u32).min(money);
+                            market::purchase(
+                                human.inventory_id,
+                                stall_inventory_id,
+                                *item,
+                                quantity,
+                                cost,
+                            )
+                        } else {
+                            Vec::new()
+                        }
+                    }
+                    _ => Vec::new(),
+                }
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Mail sent once the day's work is done, after `labour` ticks of it, handing the worker
+    /// what they made.
+    pub fn finish_work(&self, human: &Human, world: &World, labour: u32) -> Vec<Mail> {
+        match self {
+            Job::Farmer(crop_id) => world
+                .crops
+                .get(*crop_id)
+                .map(|crop| {
+                    Mail::new(
+                        crop.inventory_id,
+                        ItemMessage::Take(Item::FOOD, HARVEST, human.inventory_id),
+                    )
+                })
+                .into_iter()
+                .collect(), // TODO calculate remaining capacity in think/perceive and use that
+            Job::Woodcutter(_) => vec![Mail::new(
+                human.inventory_id,
+                ItemMessage::GiveOrDrop(Item::WOOD, WOOD_PER_DAY),
+            )],
+            Job::Artisan(workshop_index, recipe_index) => {
+                let recipe = world.items.recipe(*recipe_index);
+                let workshop_inventory_id = world.workshops[*workshop_index].inventory_id;
+                let stock = &world.inventories[workshop_inventory_id];
+                let has_tool = recipe.tool.map_or(true, |tool| {
+                    stock.count(tool) > 0 || world.inventories[human.inventory_id].count(tool) > 0
+                });
+                // as many batches as there was time and material for
+                let batches = recipe
+                    .inputs
+                    .iter()
+                    .map(|&(item, quantity)| stock.count(item) / quantity)
+                    .fold(labour / recipe.labour, u32::min);
+                if has_tool && batches > 0 {
+                    let times = |items: &Vec<(Item, u32)>| {
+                        items
+                            .iter()
+                            .map(|&(item, quantity)| (item, quantity * batches))
+                            .collect()
+                    };
+                    vec![Mail::new(
+                        workshop_inventory_id,
+                        ItemMessage::Craft(
+                            times(&recipe.inputs),
+                            times(&recipe.outputs),
+                            human.inventory_id,
+                        ),
+                    )]
+                } else {
+                    Vec::new()
+                }
+            }
+            Job::Merchant(_) => Vec::new(),
+        }
+    }
+}
+
+impl Save for Job {
+    fn save(&self, writer: &mut SaveWriter) {
+        match self {
+            Job::Farmer(crop_id) => {
+                writer.put("farmer");
+                writer.put(crop_id);
+            }
+            Job::Woodcutter(trees) => {
+                writer.put("woodcutter");
+                trees.save(writer);
+            }
+            Job::Artisan(workshop_index, recipe_index) => {
+                writer.put("artisan");
+                writer.put(workshop_index);
+                writer.put(recipe_index);
+            }
+            Job::Merchant(item) => {
+                writer.put("merchant");
+                writer.put(item);
+            }
+        }
+    }
+
+    fn load(reader: &mut SaveReader) -> Result<Job, SaveError> {
+        let kind: String = reader.get()?;
+        match kind.as_str() {
+            "farmer" => Ok(Job::Farmer(reader.get()?)),
+            "woodcutter" => Ok(Job::Woodcutter(TilePoint::load(reader)?)),
+            "artisan" => Ok(Job::Artisan(reader.get()?, reader.get()?)),
+            "merchant" => Ok(Job::Merchant(reader.get()?)),
+            _ => Err(SaveError::Format(format!("unknown job '{}'", kind))),
+        }
+    }
+}
@@ -0,0 +1,194 @@
+use std::collections::BTreeMap;
+
+use quicksilver::geom::Vector;
+
+use crate::belief::{Belief, Beliefs, ALL_BELIEFS};
+use crate::event::EventRecord;
+use crate::household::HouseholdId;
+use crate::save::{Save, SaveError, SaveReader, SaveWriter};
+use crate::world::{ContainerId, TICKS_PER_DAY};
+
+const NEWS_DAYS: u64 = 7; // how long something stays worth talking about
+
+/// What someone last knew of a container's stock, and when that was true.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct StoreReport {
+    pub location: Vector,
+    pub household: HouseholdId, // whose it is
+    pub food: u32,
+    pub seen: u64, // tick it was seen, however long the report took to get here
+}
+
+/// Everything a human has seen for themselves or been told: where containers are and what
+/// was in them, and what's happened lately. Second-hand knowledge can be out of date, or
+/// simply wrong by the time it's acted on.
+pub struct Knowledge {
+    stores: BTreeMap<ContainerId, StoreReport>,
+    news: Vec<EventRecord>, // oldest first
+}
+
+/// What one human tells another in conversation.
+pub struct Gossip {
+    pub opinions: Vec<(Belief, f32)>,
+    pub stores: Vec<(ContainerId, StoreReport)>,
+    pub news: Vec<EventRecord>,
+}
+
+impl Gossip {
+    /// How far apart two people's opinions are on whatever they disagree about most, from 0 to 2.
+    pub fn disagreement(&self, other: &Gossip) -> f32 {
+        self.opinions
+            .iter()
+            .filter_map(|&(belief, weight)| {
+                other
+                    .opinions
+                    .iter()
+                    .find(|&&(other_belief, _)| other_belief == belief)
+                    .map(|&(_, other_weight)| (weight - other_weight).abs())
+            })
+            .fold(0.0, f32::max)
+    }
+}
+
+impl Knowledge {
+    pub fn new() -> Knowledge {
+        Knowledge {
+            stores: BTreeMap::new(),
+            news: Vec::new(),
+        }
+    }
+
+    pub fn stores<'a>(&'a self) -> impl Iterator<Item = (ContainerId, &'a StoreReport)> + 'a {
+        self.stores.iter().map(|(&id, report)| (id, report))
+    }
+
+    pub fn news(&self) -> &Vec<EventRecord> {
+        &self.news
+    }
+
+    /// Takes in a report of a container, unless what they already know is fresher.
+    pub fn learn_store(&mut self, container_id: ContainerId, report: StoreReport) {
+        let known = self.stores.entry(container_id).or_insert(report);
+        if report.seen > known.seen {
+            *known = report;
+        }
+    }
+
+    pub fn forget_store(&mut self, container_id: ContainerId) {
+        self.stores.remove(&container_id);
+    }
+
+    /// Remembers something that happened; false if they'd already heard.
+    pub fn learn_news(&mut self, record: &EventRecord) -> bool {
+        if self.news.contains(record) {
+            false
+        } else {
+            let at = self
+                .news
+                .iter()
+                .position(|known| known.tick > record.tick)
+                .unwrap_or(self.news.len());
+            self.news.insert(at, *record);
+            true
+        }
+    }
+
+    /// Stops talking about old news.
+    pub fn forget_old_news(&mut self, now: u64) {
+        self.news
+            .retain(|record| record.tick + NEWS_DAYS * TICKS_PER_DAY > now);
+    }
+
+    /// Everything they'd pass on, along with what they believe.
+    pub fn gossip(&self, beliefs: &Beliefs) -> Gossip {
+        Gossip {
+            opinions: ALL_BELIEFS
+                .iter()
+                .map(|&belief| (belief, beliefs.weight(belief)))
+                .collect(),
+            stores: self.stores().map(|(id, report)| (id, *report)).collect(),
+            news: self.news.clone(),
+        }
+    }
+
+    pub fn description_lines(&self) -> Vec<String> {
+        vec![format!(
+            "Knows of {} stores, {} bits of news",
+            self.stores.len(),
+            self.news.len()
+        )]
+    }
+}
+
+impl Save for StoreReport {
+    fn save(&self, writer: &mut SaveWriter) {
+        writer.put_vector(self.location);
+        writer.put(self.household);
+        writer.put(self.food);
+        writer.put(self.seen);
+    }
+
+    fn load(reader: &mut SaveReader) -> Result<StoreReport, SaveError> {
+        Ok(StoreReport {
+            location: reader.get_vector()?,
+            household: reader.get()?,
+            food: reader.get()?,
+            seen: reader.get()?,
+        })
+    }
+}
+
+impl Save for Knowledge {
+    fn save(&self, writer: &mut SaveWriter) {
+        writer.tag("knowledge");
+        writer.put(self.stores.len());
+        for (container_id, report) in &self.stores {
+            writer.put(container_id);
+            report.save(writer);
+        }
+        writer.put_list("news", &self.news);
+    }
+
+    fn load(reader: &mut SaveReader) -> Result<Knowledge, SaveError> {
+        reader.expect("knowledge")?;
+        let mut knowledge = Knowledge::new();
+        let len: usize = reader.get()?;
+        for _ in 0..len {
+            let container_id = reader.get()?;
+            knowledge
+                .stores
+                .insert(container_id, StoreReport::load(reader)?);
+        }
+        knowledge.news = reader.get_list("news")?;
+        Ok(knowledge)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gossip(opinions: Vec<(Belief, f32)>) -> Gossip {
+        Gossip {
+            opinions: opinions,
+            stores: Vec::new(),
+            news: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn disagreement_over_shared_beliefs_only() {
+        let believer = gossip(vec![
+            (Belief::GodExists, 1.0),
+            (Belief::GodIsBenevolent, 0.5),
+        ]);
+        let doubter = gossip(vec![
+            (Belief::GodExists, -0.5),
+            (Belief::GodGrantsHarvest, -1.0),
+        ]);
+        assert_eq!(believer.disagreement(&doubter), 1.5);
+        assert_eq!(doubter.disagreement(&believer), 1.5);
+        assert_eq!(believer.disagreement(&believer), 0.0);
+        assert_eq!(believer.disagreement(&gossip(Vec::new())), 0.0);
+    }
+}
@@ -0,0 +1,26 @@
+pub mod belief;
+pub mod biography;
+pub mod catalogue;
+pub mod entity;
+pub mod event;
+pub mod gamestate;
+pub mod geography;
+pub mod household;
+pub mod human;
+pub mod item;
+pub mod job;
+pub mod knowledge;
+pub mod market;
+pub mod miracle;
+pub mod personality;
+pub mod plan;
+pub mod plant;
+pub mod power;
+pub mod random;
+pub mod relationship;
+pub mod save;
+pub mod scenario;
+pub mod simulation;
+pub mod value;
+pub mod weather;
+pub mod world;
@@ -1,11 +1,3 @@
-mod gamestate;
-mod geography;
-mod human;
-mod item;
-mod plant;
-mod weather;
-mod world;
-
 use rand::distributions::{Distribution, Normal, Uniform};
 use rayon::prelude::*;
 
@@ -18,6 +10,7 @@ use quicksilver::{
 };
 
 use std::collections::HashMap;
+use std::fmt::Display;
 use std::fs::File;
 use std::io::prelude::*;
 use std::io::BufReader;
@@ -28,15 +21,23 @@ use std::u32;
 
 use rand::prelude::*;
 
-use crate::gamestate::GameState;
-use crate::geography::{Geography, TilePoint};
-use crate::human::{Human, Job, Mind};
-use crate::item::{Inventory, Item, ItemMessage};
-use crate::plant::Crop;
-use crate::weather::Weather;
-use crate::world::{Container, Time, World};
+use esytheism::catalogue::{ItemCatalogue, CATALOGUE_FILE};
+use esytheism::gamestate::GameState;
+use esytheism::geography::{Geography, TilePoint};
+use esytheism::human::{Human, HumanId, Mind};
+use esytheism::item::{Inventory, InventoryId, Item, ItemMessage};
+use esytheism::market::{DEFAULT_PRICE, HISTORY_LEN};
+use esytheism::miracle::Miracle;
+use esytheism::plant::{Crop, CropId};
+use esytheism::scenario::Scenario;
+use esytheism::weather::Weather;
+use esytheism::world::{Container, ContainerId, Time, World};
 
 pub const SCREEN_SIZE: Vector = Vector {x: 1200.0, y: 900.0};
+const SAVE_PATH: &str = "esytheism.sav";
+const DEFAULT_SCENARIO: &str = "data/test.scenario";
+const MIRACLE_ITEMS: u32 = 10; // conjured or destroyed per keypress
+const POWER_GAUGE_MAX: f32 = 200.0; // power at which the gauge shows full
 
 struct Engine {
     game_state: GameState,
@@ -49,13 +50,16 @@ struct Engine {
     camera: Vector, // represents center of window
 
     selected: Selected,
+    show_biography: bool, // for a selected human, instead of how they're doing now
 }
 
 enum Selected {
     None,
-    Human(usize),
-    Crop(usize),
-    Container(usize),
+    Human(HumanId),
+    Crop(CropId),
+    Container(ContainerId),
+    Market(usize),
+    Workshop(usize),
 }
 
 impl Engine {
@@ -64,13 +68,58 @@ impl Engine {
         let top_left = (top_left - camera_top_left) / self.scale;
         Rectangle::new(top_left, size / self.scale)
     }
+
+    fn selected_inventory(&self) -> Option<InventoryId> {
+        let world = &self.game_state.world;
+        match self.selected {
+            Selected::None => None,
+            Selected::Human(human_id) => Some(world.humans[human_id].inventory_id),
+            Selected::Crop(crop_id) => Some(world.crops[crop_id].inventory_id),
+            Selected::Container(container_id) => Some(world.containers[container_id].inventory_id),
+            Selected::Market(_) => None,
+            Selected::Workshop(index) => Some(world.workshops[index].inventory_id),
+        }
+    }
+
+    fn work_miracle(&mut self, miracle: Option<Miracle>) {
+        match miracle {
+            Some(miracle) => {
+                if let Err(err) = self.game_state.perform_miracle(miracle) {
+                    eprintln!("miracle failed: {}", err);
+                }
+            },
+            None => eprintln!("select a target for that miracle first"),
+        }
+    }
 }
 
 impl State for Engine {
     fn new() -> Result<Engine> {
         let font = Asset::new(Font::load("anonymous_pro.ttf"));
+        // scenario path is relative to the static folder, like all other assets
+        let scenario_path = std::env::args().nth(1).unwrap_or(DEFAULT_SCENARIO.to_string());
+        let scenario_dir = std::path::Path::new(&scenario_path)
+            .parent()
+            .unwrap_or(std::path::Path::new(""));
+        let catalogue_path = scenario_dir.join(CATALOGUE_FILE);
+        let items = load_file(&catalogue_path)
+            .map(|data| ItemCatalogue::parse(&String::from_utf8_lossy(&data)))
+            .wait()?
+            .map_err(|err| invalid("catalogue", catalogue_path.display(), err))?;
+        let scenario = load_file(&scenario_path)
+            .map(|data| Scenario::parse(&String::from_utf8_lossy(&data), &items))
+            .wait()?
+            .map_err(|err| invalid("scenario", &scenario_path, err))?;
+        let map_path = scenario_dir.join(&scenario.map.path);
+        let (width, height) = (scenario.map.width, scenario.map.height);
+        let geo = load_file(&map_path)
+            .map(|data| Geography::from_data(width, height, &data))
+            .wait()?
+            .map_err(|err| invalid("map", map_path.display(), err))?;
+        let seed = scenario.seed.unwrap_or_else(|| thread_rng().gen());
+        let game_state = GameState::from_scenario(geo, items, scenario, Some(seed));
         Ok(Engine {
-            game_state: GameState::new(),
+            game_state: game_state,
             font: font,
             paused: false,
             updates_per_tick: 1,
@@ -78,6 +127,7 @@ impl State for Engine {
             camera: SCREEN_SIZE / 2,
             scale: 1.0,
             selected: Selected::None,
+            show_biography: false,
         })
     }
 
@@ -96,20 +146,73 @@ impl State for Engine {
                     self.updates_per_tick /= 2;
                 }
             },
+            Event::Key(Key::F5, ButtonState::Pressed) => {
+                match self.game_state.save_to_path(SAVE_PATH) {
+                    Ok(()) => println!("saved to {}", SAVE_PATH),
+                    Err(err) => eprintln!("failed to save to {}: {}", SAVE_PATH, err),
+                }
+            },
+            Event::Key(Key::F9, ButtonState::Pressed) => {
+                match GameState::load_from_path(SAVE_PATH) {
+                    Ok(game_state) => {
+                        self.game_state = game_state;
+                        self.selected = Selected::None;
+                        println!("loaded {}", SAVE_PATH);
+                    },
+                    Err(err) => eprintln!("failed to load {}: {}", SAVE_PATH, err),
+                }
+            },
+            Event::Key(Key::L, ButtonState::Pressed) => {
+                self.show_biography = !self.show_biography;
+            },
+            Event::Key(Key::R, ButtonState::Pressed) => self.work_miracle(Some(Miracle::Rain)),
+            Event::Key(Key::S, ButtonState::Pressed) => self.work_miracle(Some(Miracle::Sunshine)),
+            Event::Key(Key::B, ButtonState::Pressed) => {
+                let miracle = match self.selected {
+                    Selected::Crop(crop_id) => Some(Miracle::BlessCrop(crop_id)),
+                    _ => None,
+                };
+                self.work_miracle(miracle);
+            },
+            Event::Key(Key::F, ButtonState::Pressed) => {
+                let miracle = self.selected_inventory().map(|id| Miracle::Conjure(id, Item::FOOD, MIRACLE_ITEMS));
+                self.work_miracle(miracle);
+            },
+            Event::Key(Key::X, ButtonState::Pressed) => {
+                let miracle = self.selected_inventory().map(|id| Miracle::Destroy(id, Item::FOOD, MIRACLE_ITEMS));
+                self.work_miracle(miracle);
+            },
+            Event::Key(Key::H, ButtonState::Pressed) => {
+                let miracle = match self.selected {
+                    Selected::Human(human_id) => Some(Miracle::Heal(human_id)),
+                    _ => None,
+                };
+                self.work_miracle(miracle);
+            },
+            Event::Key(Key::T, ButtonState::Pressed) => {
+                let miracle = match self.selected {
+                    Selected::Human(human_id) => Some(Miracle::Exhaust(human_id)),
+                    _ => None,
+                };
+                self.work_miracle(miracle);
+            },
             Event::MouseButton(MouseButton::Left, ButtonState::Pressed) => {
                 let camera_top_left = self.camera - (SCREEN_SIZE * self.scale / 2);
                 self.camera = window.mouse().pos() * self.scale + camera_top_left;
 
                 let click_tile = TilePoint::from_vector(&(self.camera / 20.0));
-                let human_loc = TilePoint::from_vector(&self.game_state.world.humans[0].location);
                 // TODO track click based on actual location and size of thing, not tile approximation
                 self.selected = 
-                    if let Some((index, _)) = self.game_state.world.humans.iter().enumerate().find(|(_, human)| TilePoint::from_vector(&human.location) == click_tile) {
-                        Selected::Human(index)
-                    } else if let Some((index, _)) = self.game_state.world.crops.iter().enumerate().find(|(_, crop)| TilePoint::from_vector(&crop.location) == click_tile) {
-                        Selected::Crop(index)
-                    } else if let Some((index, _)) = self.game_state.world.containers.iter().enumerate().find(|(_, container)| TilePoint::from_vector(&container.location) == click_tile) {
-                        Selected::Crop(index)
+                    if let Some((human_id, _)) = self.game_state.world.humans.iter().find(|(_, human)| TilePoint::from_vector(&human.location) == click_tile) {
+                        Selected::Human(human_id)
+                    } else if let Some((crop_id, _)) = self.game_state.world.crops.iter().find(|(_, crop)| TilePoint::from_vector(&crop.location) == click_tile) {
+                        Selected::Crop(crop_id)
+                    } else if let Some((container_id, _)) = self.game_state.world.containers.iter().find(|(_, container)| TilePoint::from_vector(&container.location) == click_tile) {
+                        Selected::Container(container_id)
+                    } else if let Some((index, _)) = self.game_state.world.markets.iter().enumerate().find(|(_, market)| TilePoint::from_vector(&market.location) == click_tile) {
+                        Selected::Market(index)
+                    } else if let Some((index, _)) = self.game_state.world.workshops.iter().enumerate().find(|(_, workshop)| TilePoint::from_vector(&workshop.location) == click_tile) {
+                        Selected::Workshop(index)
                     } else {
                         Selected::None
                     };
@@ -135,6 +238,12 @@ impl State for Engine {
                 == 0
         {
             self.game_state.update();
+            if let Selected::Human(human_id) = self.selected {
+                // they may have died
+                if !self.game_state.world.humans.contains(human_id) {
+                    self.selected = Selected::None;
+                }
+            }
         }
         if !self.paused {
             let updates_per_tick = self.updates_per_tick;
@@ -153,9 +262,13 @@ impl State for Engine {
                 let tile = &self.game_state.world.geography.tiles[x][y];
                 window.draw(
                     &self.apply_camera(Vector::new(x as u32 * 20, y as u32 * 20), Vector::new(20, 20)),
-                    Col(match tile.terrain_cost {
-                        1 => Color::from_rgba(191, 156, 116, 1.0),
-                        _ => Color::from_rgba(127, 234, 117, 1.0),
+                    Col(if tile.water {
+                        Color::from_rgba(80, 140, 220, 1.0)
+                    } else {
+                        match tile.terrain_cost {
+                            1 => Color::from_rgba(191, 156, 116, 1.0),
+                            _ => Color::from_rgba(127, 234, 117, 1.0),
+                        }
                     }),
                 );
             }
@@ -192,15 +305,55 @@ impl State for Engine {
             }
         }
 
+        // draw shrines
+        for shrine in &self.game_state.world.shrines {
+            window.draw(
+                &self.apply_camera(shrine.location * 20 - Vector::new(4, 4), Vector::new(8, 8)),
+                Col(Color::WHITE),
+            );
+        }
+
+        // draw wells
+        for well in &self.game_state.world.wells {
+            window.draw(
+                &self.apply_camera(well.location * 20 - Vector::new(4, 4), Vector::new(8, 8)),
+                Col(Color::BLUE),
+            );
+        }
+
+        // draw beds
+        for bed in &self.game_state.world.beds {
+            window.draw(
+                &self.apply_camera(bed.location * 20 - Vector::new(3, 5), Vector::new(6, 10)),
+                Col(Color::from_rgba(200, 170, 120, 1.0)),
+            );
+        }
+
+        // draw markets
+        for market in &self.game_state.world.markets {
+            window.draw(
+                &self.apply_camera(market.location * 20 - Vector::new(6, 6), Vector::new(12, 12)),
+                Col(Color::ORANGE),
+            );
+        }
+
+        // draw workshops
+        for workshop in &self.game_state.world.workshops {
+            window.draw(
+                &self.apply_camera(workshop.location * 20 - Vector::new(6, 6), Vector::new(12, 12)),
+                Col(Color::from_rgba(139, 90, 43, 1.0)),
+            );
+        }
+
         // draw humans
-        for human in &self.game_state.world.humans {
+        for human in self.game_state.world.humans.values() {
             window.draw(
                 &self.apply_camera(human.location * 20 - Vector::new(2, 2), Vector::new(4, 4)),
                 Col(Color::RED),
             );
         }
 
-        for mind in &self.game_state.minds {
+        for mind in self.game_state.minds.values() {
             self.font.execute(|font| {
                 window.draw(&Rectangle::new(((SCREEN_SIZE.x - 400.0) / 2.0, SCREEN_SIZE.y - 50.0), (400, 50)), Col(Color::BLACK));
                 let style = FontStyle::new(48.0, Color::WHITE);
@@ -214,6 +367,23 @@ impl State for Engine {
             });
         }
 
+        let power = self.game_state.divine_power().amount();
+        let gauge_fill = (power / POWER_GAUGE_MAX).min(1.0).max(0.0);
+        window.draw(&Rectangle::new((SCREEN_SIZE.x - 400.0, 0), (166, 20)), Col(Color::BLACK));
+        window.draw(
+            &Rectangle::new((SCREEN_SIZE.x - 398.0, 2), (162.0 * gauge_fill, 16)),
+            Col(Color::from_rgba(212, 175, 55, 1.0)),
+        );
+        self.font.execute(|font| {
+            let style = FontStyle::new(18.0, Color::WHITE);
+            let power_img = font.render(&format!("Power: {:.0}", power), &style).unwrap();
+            window.draw(
+                &Rectangle::new((SCREEN_SIZE.x - 396.0, 1), power_img.area().size()),
+                Img(&power_img),
+            );
+            Ok(())
+        });
+
         let world_time = &self.game_state.world.time;
         self.font.execute(|font| {
             window.draw(&Rectangle::new((SCREEN_SIZE.x - 230.0, 0), (230, 20)), Col(Color::BLACK));
@@ -227,8 +397,8 @@ impl State for Engine {
         });
 
         let lines = match self.selected {
-            Selected::Human(index) => {
-                let human = &self.game_state.world.humans[index];
+            Selected::Human(human_id) => {
+                let human = &self.game_state.world.humans[human_id];
                 let top_left = human.location * 20 - Vector::new(3, 3);
                 let top_right = top_left + Vector::new(4, 0);
                 let bottom_left = top_left + Vector::new(0, 4);
@@ -240,11 +410,31 @@ impl State for Engine {
                 window.draw(&self.apply_camera(bottom_left, horiz_size), Col(Color::YELLOW));
                 window.draw(&self.apply_camera(top_left, vert_size), Col(Color::YELLOW));
 
-                Some(human.description_lines(&self.game_state.world))
+                if self.show_biography {
+                    let mut lines = vec![format!("The life of {}", human.name())];
+                    lines.append(&mut human.biography().description_lines(&self.game_state.world));
+                    Some(lines)
+                } else {
+                    let mut lines = human.description_lines(&self.game_state.world);
+                    lines.append(&mut self.game_state.minds[human_id].description_lines());
+                    Some(lines)
+                }
+            },
+            Selected::Crop(crop_id) => {
+                Some(self.game_state.world.crops[crop_id].description_lines(&self.game_state.world))
+            },
+            Selected::Container(container_id) => {
+                Some(self.game_state.world.containers[container_id].description_lines(&self.game_state.world))
             },
-            // TODO crop and containers
-            _ => None,
+            Selected::Market(index) => {
+                Some(self.game_state.world.markets[index].description_lines(&self.game_state.world))
+            },
+            Selected::Workshop(index) => {
+                Some(self.game_state.world.workshops[index].description_lines(&self.game_state.world))
+            },
+            Selected::None => None,
         };
+        let panel_bottom = lines.as_ref().map_or(4, |lines| (9 + lines.len() * 20) as u32);
         if let Some(lines) = lines {
             let height = (5 + lines.len() * 20) as u32;
             window.draw(
@@ -263,11 +453,38 @@ impl State for Engine {
                 Ok(())
             });
         }
+
+        // food price chart for the selected market, one bar per hour
+        if let Selected::Market(index) = self.selected {
+            let history = self.game_state.world.markets[index].price_history(Item::FOOD);
+            let highest = history.iter().cloned().fold(DEFAULT_PRICE, f32::max);
+            let chart_top = panel_bottom as f32 + 4.0;
+            window.draw(
+                &Rectangle::new((4.0, chart_top), (200, 60)),
+                Col(Color::from_rgba(0, 0, 0, 0.5)),
+            );
+            let bar_width = 200.0 / HISTORY_LEN as f32;
+            for (hour, price) in history.iter().enumerate() {
+                let bar_height = 56.0 * price / highest;
+                window.draw(
+                    &Rectangle::new(
+                        (4.0 + hour as f32 * bar_width, chart_top + 58.0 - bar_height),
+                        (bar_width, bar_height),
+                    ),
+                    Col(Color::ORANGE),
+                );
+            }
+        }
         Ok(())
     }
 
 }
 
+// a file that loaded but didn't make sense, reported the way quicksilver reports its own errors
+fn invalid(kind: &str, path: impl Display, err: impl Display) -> quicksilver::Error {
+    quicksilver::Error::ContextError(format!("invalid {} {}: {}", kind, path, err))
+}
+
 fn main() {
     run::<Engine>("Esytheism", SCREEN_SIZE, Settings::default());
 }
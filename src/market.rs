@@ -0,0 +1,408 @@
+use std::collections::BTreeMap;
+use std::hash::{Hash, Hasher};
+use std::u32;
+
+use quicksilver::geom::Vector;
+
+use crate::entity::Arena;
+use crate::item::{Inventory, InventoryId, Item, ItemMessage, Mail, Senders};
+use crate::save::{Save, SaveError, SaveReader, SaveWriter};
+use crate::world::{World, TICKS_PER_MINUTE};
+
+pub const STALL_CAPACITY: f32 = 1e6;
+pub const SALE_TICKS: u64 = TICKS_PER_MINUTE as u64 * 60 * 8; // a stall is rented for 8 hours
+const PRICE_STEP: f32 = 0.005; // per tick
+const MIN_PRICE: f32 = 0.05;
+pub const DEFAULT_PRICE: f32 = 1.0;
+const HISTORY_TICKS: u64 = TICKS_PER_MINUTE as u64 * 60; // price history is sampled hourly
+pub const HISTORY_LEN: usize = 24 * 7;
+
+#[derive(Eq, PartialEq, PartialOrd, Ord)]
+pub enum MarketMessage {
+    OpenStall(usize, InventoryId, Item, u32), // market index, seller inventory id, item, quantity
+    Bought(usize, InventoryId, InventoryId), // market index, stall inventory id, buyer inventory id
+}
+
+/// The mail that buys `quantity` of `item` from a stall for `cost`. The stall sends the money
+/// back if the goods are gone by the time it gets there.
+pub fn purchase(
+    buyer_inventory_id: InventoryId,
+    stall_inventory_id: InventoryId,
+    item: Item,
+    quantity: u32,
+    cost: u32,
+) -> Vec<Mail> {
+    vec![
+        Mail::new(buyer_inventory_id, ItemMessage::Remove(Item::MONEY, cost)),
+        Mail::new(
+            stall_inventory_id,
+            ItemMessage::Trade((Item::MONEY, cost), (item, quantity), buyer_inventory_id),
+        ),
+    ]
+}
+
+/// A place where humans rent stalls to sell their surplus to each other for money.
+pub struct Market {
+    pub location: Vector,
+    pub stalls: Vec<Stall>,
+    price_history: BTreeMap<Item, Vec<f32>>,
+}
+
+pub struct Stall {
+    pub inventory_id: InventoryId,
+    sale: Option<Sale>,
+}
+
+// Sellers hand their goods over when the stall opens, and get the takings plus whatever didn't
+// sell back when it closes, wherever they are by then.
+struct Sale {
+    seller_inventory_id: InventoryId,
+    item: Item,
+    price: f32,
+    opened: u64,
+    initial_stock: u32,
+}
+
+impl Stall {
+    pub fn new(inventory_id: InventoryId) -> Stall {
+        Stall {
+            inventory_id: inventory_id,
+            sale: None,
+        }
+    }
+
+    /// What's for sale here, and for how much.
+    pub fn offer(&self) -> Option<(Item, f32)> {
+        self.sale.as_ref().map(|sale| (sale.item, sale.price))
+    }
+}
+
+impl Market {
+    pub fn new(location: Vector, stall_inventory_ids: Vec<InventoryId>) -> Market {
+        Market {
+            location: location,
+            stalls: stall_inventory_ids.into_iter().map(Stall::new).collect(),
+            price_history: BTreeMap::new(),
+        }
+    }
+
+    pub fn price_history(&self, item: Item) -> &[f32] {
+        self.price_history
+            .get(&item)
+            .map(|history| history.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// The last recorded price, for anyone deciding whether a trip to market is worth it.
+    pub fn going_price(&self, item: Item) -> f32 {
+        self.price_history(item)
+            .last()
+            .cloned()
+            .unwrap_or(DEFAULT_PRICE)
+    }
+
+    /// Mean of the recorded prices, what a merchant reckons the goods are really worth.
+    pub fn average_price(&self, item: Item) -> f32 {
+        let history = self.price_history(item);
+        if history.is_empty() {
+            DEFAULT_PRICE
+        } else {
+            history.iter().sum::<f32>() / history.len() as f32
+        }
+    }
+
+    /// Every stall with `item` in stock, as (stall inventory id, seller inventory id, price,
+    /// stock).
+    pub fn offers(
+        &self,
+        item: Item,
+        inventories: &Arena<Inventory>,
+    ) -> Vec<(InventoryId, InventoryId, f32, u32)> {
+        self.stalls
+            .iter()
+            .filter_map(|stall| match &stall.sale {
+                Some(sale) if sale.item == item => {
+                    let stock = inventories[stall.inventory_id].count(item);
+                    if stock > 0 {
+                        Some((
+                            stall.inventory_id,
+                            sale.seller_inventory_id,
+                            sale.price,
+                            stock,
+                        ))
+                    } else {
+                        None
+                    }
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Cheapest stall with `item` in stock, as (stall inventory id, price, stock).
+    pub fn cheapest(
+        &self,
+        item: Item,
+        inventories: &Arena<Inventory>,
+    ) -> Option<(InventoryId, f32, u32)> {
+        self.offers(item, inventories)
+            .into_iter()
+            .map(|(stall_inventory_id, _, price, stock)| (stall_inventory_id, price, stock))
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+    }
+
+    /// Whoever is selling at the stall with `stall_inventory_id`, if it's rented out.
+    pub fn seller(&self, stall_inventory_id: InventoryId) -> Option<InventoryId> {
+        self.stalls
+            .iter()
+            .find(|stall| stall.inventory_id == stall_inventory_id)
+            .and_then(|stall| stall.sale.as_ref())
+            .map(|sale| sale.seller_inventory_id)
+    }
+
+    /// Rents out a free stall, if there is one, and has the seller's goods moved into it.
+    pub fn open_stall(
+        &mut self,
+        now: u64,
+        seller_inventory_id: InventoryId,
+        item: Item,
+        quantity: u32,
+        senders: &Senders,
+    ) -> bool {
+        // start from whatever the going rate was last time
+        let price = self.going_price(item);
+        match self.stalls.iter_mut().find(|stall| stall.sale.is_none()) {
+            Some(stall) => {
+                senders.send(
+                    seller_inventory_id,
+                    ItemMessage::Transfer(seller_inventory_id, item, quantity, stall.inventory_id),
+                );
+                stall.sale = Some(Sale {
+                    seller_inventory_id: seller_inventory_id,
+                    item: item,
+                    price: price,
+                    opened: now,
+                    initial_stock: quantity,
+                });
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Every tick each stall nudges its price so that it's on course to sell its last item just
+    /// as its time runs out: cheaper when stock is left over, dearer when it's going too fast.
+    pub fn update(&mut self, now: u64, inventories: &Arena<Inventory>, senders: &Senders) {
+        for stall in self.stalls.iter_mut() {
+            let closed = match &mut stall.sale {
+                Some(sale) => {
+                    let elapsed = now - sale.opened;
+                    if elapsed >= SALE_TICKS {
+                        for &item in [sale.item, Item::MONEY].iter() {
+                            senders.send(
+                                stall.inventory_id,
+                                ItemMessage::Transfer(
+                                    stall.inventory_id,
+                                    item,
+                                    u32::MAX,
+                                    sale.seller_inventory_id,
+                                ),
+                            );
+                        }
+                        true
+                    } else {
+                        let remaining = 1.0 - elapsed as f32 / SALE_TICKS as f32;
+                        let target_stock = sale.initial_stock as f32 * remaining;
+                        let stock = inventories[stall.inventory_id].count(sale.item) as f32;
+                        // with nothing on the stall, sold out or not yet arrived, there's no
+                        // telling how fast it's going
+                        if stock > 0.0 {
+                            sale.price = if stock > target_stock {
+                                sale.price * (1.0 - PRICE_STEP)
+                            } else {
+                                sale.price * (1.0 + PRICE_STEP)
+                            }
+                            .max(MIN_PRICE);
+                        }
+                        false
+                    }
+                }
+                None => false,
+            };
+            if closed {
+                stall.sale = None;
+            }
+        }
+
+        if now % HISTORY_TICKS == 0 {
+            let mut prices: BTreeMap<Item, f32> = BTreeMap::new();
+            // only what's actually to be had
+            let offers = self.stalls.iter().filter_map(|stall| match stall.offer() {
+                Some((item, price)) if inventories[stall.inventory_id].count(item) > 0 => {
+                    Some((item, price))
+                }
+                _ => None,
+            });
+            for (item, price) in offers {
+                let cheapest = prices.entry(item).or_insert(price);
+                *cheapest = cheapest.min(price);
+            }
+            for (item, price) in prices {
+                let history = self.price_history.entry(item).or_insert(Vec::new());
+                history.push(price);
+                if history.len() > HISTORY_LEN {
+                    history.remove(0);
+                }
+            }
+        }
+    }
+
+    pub fn description_lines(&self, world: &World) -> Vec<String> {
+        let mut lines = vec!["Market".to_string()];
+        for stall in &self.stalls {
+            lines.push(match &stall.sale {
+                Some(sale) => format!(
+                    "{} x{} @ {:.2}",
+                    world.items.name(sale.item),
+                    world.inventories[stall.inventory_id].count(sale.item),
+                    sale.price
+                ),
+                None => "(empty stall)".to_string(),
+            });
+        }
+        lines
+    }
+}
+
+impl Hash for Market {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.location.x.to_bits().hash(state);
+        self.location.y.to_bits().hash(state);
+        for stall in &self.stalls {
+            stall.inventory_id.hash(state);
+            if let Some(sale) = &stall.sale {
+                sale.seller_inventory_id.hash(state);
+                sale.item.hash(state);
+                sale.price.to_bits().hash(state);
+                sale.opened.hash(state);
+                sale.initial_stock.hash(state);
+            }
+        }
+        for (item, history) in &self.price_history {
+            item.hash(state);
+            for price in history {
+                price.to_bits().hash(state);
+            }
+        }
+    }
+}
+
+impl Save for Stall {
+    fn save(&self, writer: &mut SaveWriter) {
+        writer.tag("stall");
+        writer.put(self.inventory_id);
+        match &self.sale {
+            Some(sale) => {
+                writer.put("sale");
+                writer.put(sale.seller_inventory_id);
+                writer.put(sale.item);
+                writer.put(sale.price);
+                writer.put(sale.opened);
+                writer.put(sale.initial_stock);
+            }
+            None => writer.put("-"),
+        }
+    }
+
+    fn load(reader: &mut SaveReader) -> Result<Stall, SaveError> {
+        reader.expect("stall")?;
+        let mut stall = Stall::new(reader.get()?);
+        if reader.get::<String>()? == "sale" {
+            stall.sale = Some(Sale {
+                seller_inventory_id: reader.get()?,
+                item: reader.get()?,
+                price: reader.get()?,
+                opened: reader.get()?,
+                initial_stock: reader.get()?,
+            });
+        }
+        Ok(stall)
+    }
+}
+
+impl Save for Market {
+    fn save(&self, writer: &mut SaveWriter) {
+        writer.tag("market");
+        writer.put_vector(self.location);
+        writer.put_list("stalls", &self.stalls);
+        writer.tag("prices");
+        writer.put(self.price_history.len());
+        for (item, history) in &self.price_history {
+            writer.put(item);
+            writer.put(history.len());
+            for price in history {
+                writer.put(price);
+            }
+        }
+    }
+
+    fn load(reader: &mut SaveReader) -> Result<Market, SaveError> {
+        reader.expect("market")?;
+        let location = reader.get_vector()?;
+        let stalls = reader.get_list("stalls")?;
+        reader.expect("prices")?;
+        let mut price_history = BTreeMap::new();
+        let len: usize = reader.get()?;
+        for _ in 0..len {
+            let item = reader.get()?;
+            let history_len: usize = reader.get()?;
+            let mut history = Vec::new();
+            for _ in 0..history_len {
+                history.push(reader.get()?);
+            }
+            price_history.insert(item, history);
+        }
+        Ok(Market {
+            location: location,
+            stalls: stalls,
+            price_history: price_history,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalogue::ItemCatalogue;
+    use crate::entity::HandleMap;
+
+    #[test]
+    fn sold_out_stall_keeps_its_price() {
+        let items = ItemCatalogue::parse(include_str!("../static/data/items.catalogue")).unwrap();
+        let mut inventories = Arena::new();
+        let seller = inventories.insert(Inventory::new(100.0));
+        let stall = inventories.insert(Inventory::new(STALL_CAPACITY));
+        let senders = HandleMap::new(); // the goods are handed over below instead
+        let mut market = Market::new(Vector::new(0.0, 0.0), vec![stall]);
+        assert!(market.open_stall(0, seller, Item::FOOD, 10, &senders));
+        inventories[stall].do_give_up_to(Item::FOOD, 10, &items);
+        for now in 1..HISTORY_TICKS + 1 {
+            market.update(now, &inventories, &senders);
+        }
+        let before = market.going_price(Item::FOOD);
+
+        // everything goes at once, with most of the sale still to run
+        inventories[stall].do_take_up_to(Item::FOOD, 10);
+        for now in HISTORY_TICKS + 1..SALE_TICKS {
+            market.update(now, &inventories, &senders);
+        }
+        let (_, price) = market.stalls[0].offer().unwrap();
+        assert!(price <= before);
+        assert_eq!(market.going_price(Item::FOOD), before);
+        assert_eq!(market.price_history(Item::FOOD).len(), 1);
+
+        market.update(SALE_TICKS, &inventories, &senders);
+        assert!(market.stalls[0].offer().is_none());
+        assert_eq!(market.going_price(Item::FOOD), before);
+    }
+}
@@ -0,0 +1,129 @@
+use std::error;
+use std::fmt;
+
+use crate::catalogue::ItemCatalogue;
+use crate::human::HumanId;
+use crate::item::{InventoryId, Item};
+use crate::plant::CropId;
+use crate::power::InsufficientPower;
+use crate::save::{Save, SaveError, SaveReader, SaveWriter};
+
+pub const BLESSING_DAYS: u32 = 3;
+pub const MIRACLE_WEATHER: f64 = 30.0;
+pub const EXHAUSTION: f32 = 50.0;
+
+/// Something the player, as the god, does directly to the world.
+#[derive(Copy, Clone, PartialEq, Hash)]
+pub enum Miracle {
+    Rain,
+    Sunshine,
+    BlessCrop(CropId),
+    Conjure(InventoryId, Item, u32),
+    Destroy(InventoryId, Item, u32),
+    Heal(HumanId),
+    Exhaust(HumanId),
+}
+
+#[derive(Debug)]
+pub enum MiracleError {
+    InvalidTarget,
+    NotEnoughPower(InsufficientPower),
+}
+
+impl fmt::Display for MiracleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MiracleError::InvalidTarget => write!(f, "nothing there to work a miracle on"),
+            MiracleError::NotEnoughPower(err) => err.fmt(f),
+        }
+    }
+}
+
+impl error::Error for MiracleError {}
+
+impl From<InsufficientPower> for MiracleError {
+    fn from(err: InsufficientPower) -> MiracleError {
+        MiracleError::NotEnoughPower(err)
+    }
+}
+
+impl Miracle {
+    /// Divine power needed to work this miracle.
+    pub fn cost(&self) -> f32 {
+        match self {
+            Miracle::Rain | Miracle::Sunshine => 30.0,
+            Miracle::BlessCrop(_) => 40.0,
+            Miracle::Conjure(_, _, count) => *count as f32,
+            Miracle::Destroy(_, _, count) => *count as f32 / 2.0,
+            Miracle::Heal(_) => 25.0,
+            Miracle::Exhaust(_) => 10.0,
+        }
+    }
+
+    /// Whether people on the receiving end would take this as a kindness.
+    pub fn is_benevolent(&self) -> bool {
+        match self {
+            Miracle::Destroy(_, _, _) | Miracle::Exhaust(_) => false,
+            _ => true,
+        }
+    }
+
+    pub fn description(&self, items: &ItemCatalogue) -> String {
+        match self {
+            Miracle::Rain => "Rain fell from a clear sky".to_string(),
+            Miracle::Sunshine => "The clouds parted".to_string(),
+            Miracle::BlessCrop(crop_id) => format!("Crop {} was blessed", crop_id.index()),
+            Miracle::Conjure(_, item, count) => format!("{} {} appeared", count, items.name(*item)),
+            Miracle::Destroy(_, item, count) => format!("{} {} vanished", count, items.name(*item)),
+            Miracle::Heal(_) => "Someone was made whole".to_string(),
+            Miracle::Exhaust(_) => "Someone was struck weary".to_string(),
+        }
+    }
+}
+
+impl Save for Miracle {
+    fn save(&self, writer: &mut SaveWriter) {
+        match self {
+            Miracle::Rain => writer.put("rain"),
+            Miracle::Sunshine => writer.put("sunshine"),
+            Miracle::BlessCrop(crop_id) => {
+                writer.put("bless_crop");
+                writer.put(crop_id);
+            }
+            Miracle::Conjure(inventory_id, item, count) => {
+                writer.put("conjure");
+                writer.put(inventory_id);
+                writer.put(item);
+                writer.put(count);
+            }
+            Miracle::Destroy(inventory_id, item, count) => {
+                writer.put("destroy");
+                writer.put(inventory_id);
+                writer.put(item);
+                writer.put(count);
+            }
+            Miracle::Heal(human_id) => {
+                writer.put("heal");
+                writer.put(human_id);
+            }
+            Miracle::Exhaust(human_id) => {
+                writer.put("exhaust");
+                writer.put(human_id);
+            }
+        }
+    }
+
+    fn load(reader: &mut SaveReader) -> Result<Miracle, SaveError> {
+        let kind: String = reader.get()?;
+        Ok(match kind.as_str() {
+            "rain" => Miracle::Rain,
+            "sunshine" => Miracle::Sunshine,
+            "bless_crop" => Miracle::BlessCrop(reader.get()?),
+            "conjure" => Miracle::Conjure(reader.get()?, reader.get()?, reader.get()?),
+            "destroy" => Miracle::Destroy(reader.get()?, reader.get()?, reader.get()?),
+            "heal" => Miracle::Heal(reader.get()?),
+            "exhaust" => Miracle::Exhaust(reader.get()?),
+            _ => return Err(SaveError::Format(format!("unknown miracle '{}'", kind))),
+        })
+    }
+}
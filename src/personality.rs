@@ -0,0 +1,139 @@
+use std::hash::{Hash, Hasher};
+
+use rand::distributions::{Distribution, Normal};
+use rand::Rng;
+
+use crate::save::{Save, SaveError, SaveReader, SaveWriter};
+
+const SPREAD: f64 = 0.15; // of each trait around the average
+const NOTABLE: f32 = 0.4; // lean past which a trait is worth mentioning
+
+/// The temperament someone is born with, which colours every decision their `Mind` makes. Each
+/// trait runs from 0 to 1, with 0.5 being average.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Personality {
+    pub diligence: f32,   // starting work early, staying late and staying up
+    pub gluttony: f32,    // eating early, and eating a lot
+    pub sociability: f32, // stopping to talk
+    pub piety: f32,       // praying, however little they believe
+    pub curiosity: f32,   // wandering off to see what's about
+}
+
+impl Personality {
+    pub fn random<R: Rng>(rng: &mut R) -> Personality {
+        let normal = Normal::new(0.5, SPREAD);
+        let mut sample = || (normal.sample(rng) as f32).max(0.0).min(1.0);
+        Personality {
+            diligence: sample(),
+            gluttony: sample(),
+            sociability: sample(),
+            piety: sample(),
+            curiosity: sample(),
+        }
+    }
+
+    /// How far a trait is from average, from -1 to 1.
+    pub fn lean(value: f32) -> f32 {
+        value * 2.0 - 1.0
+    }
+
+    /// How badly two temperaments rub along: the most they differ in any one trait, from 0 to 1.
+    pub fn clash(&self, other: &Personality) -> f32 {
+        [
+            self.diligence - other.diligence,
+            self.gluttony - other.gluttony,
+            self.sociability - other.sociability,
+            self.piety - other.piety,
+            self.curiosity - other.curiosity,
+        ]
+        .iter()
+        .fold(0.0, |clash, difference| difference.abs().max(clash))
+    }
+
+    /// The traits they stand out for, as someone might describe them.
+    pub fn description(&self) -> String {
+        let words: Vec<&str> = [
+            (self.diligence, "lazy", "hard-working"),
+            (self.gluttony, "frugal", "greedy"),
+            (self.sociability, "shy", "chatty"),
+            (self.piety, "worldly", "devout"),
+            (self.curiosity, "homely", "curious"),
+        ]
+        .iter()
+        .filter_map(|&(value, low, high)| {
+            let lean = Personality::lean(value);
+            if lean <= -NOTABLE {
+                Some(low)
+            } else if lean >= NOTABLE {
+                Some(high)
+            } else {
+                None
+            }
+        })
+        .collect();
+        if words.is_empty() {
+            "even-tempered".to_string()
+        } else {
+            words.join(", ")
+        }
+    }
+}
+
+impl Hash for Personality {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.diligence.to_bits().hash(state);
+        self.gluttony.to_bits().hash(state);
+        self.sociability.to_bits().hash(state);
+        self.piety.to_bits().hash(state);
+        self.curiosity.to_bits().hash(state);
+    }
+}
+
+impl Save for Personality {
+    fn save(&self, writer: &mut SaveWriter) {
+        writer.tag("personality");
+        writer.put(self.diligence);
+        writer.put(self.gluttony);
+        writer.put(self.sociability);
+        writer.put(self.piety);
+        writer.put(self.curiosity);
+    }
+
+    fn load(reader: &mut SaveReader) -> Result<Personality, SaveError> {
+        reader.expect("personality")?;
+        Ok(Personality {
+            diligence: reader.get()?,
+            gluttony: reader.get()?,
+            sociability: reader.get()?,
+            piety: reader.get()?,
+            curiosity: reader.get()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn average() -> Personality {
+        Personality {
+            diligence: 0.5,
+            gluttony: 0.5,
+            sociability: 0.5,
+            piety: 0.5,
+            curiosity: 0.5,
+        }
+    }
+
+    #[test]
+    fn clash_is_the_widest_difference() {
+        assert_eq!(average().clash(&average()), 0.0);
+        let other = Personality {
+            gluttony: 0.3,
+            piety: 1.0,
+            ..average()
+        };
+        assert_eq!(average().clash(&other), 0.5);
+        assert_eq!(other.clash(&average()), 0.5);
+    }
+}
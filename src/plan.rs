@@ -0,0 +1,451 @@
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, BinaryHeap};
+
+use quicksilver::geom::Vector;
+
+use crate::item::Item;
+use crate::save::{Save, SaveError, SaveReader, SaveWriter};
+use crate::world::ContainerId;
+
+const MAX_STEPS: usize = 8; // past this they'd rather not bother
+
+/// Somewhere a plan takes someone.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Place {
+    Home,
+    Container(ContainerId),
+    Market(usize),
+}
+
+/// Something true of a human and the world around them, as far as they know.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Fact {
+    At(Place),
+    Carrying(Item),             // enough of it for what they're after
+    Stocked(ContainerId, Item), // the container has enough of it to spare
+    HasSpace(ContainerId),      // room for some of what they're putting away
+    Holding(Item),              // one to hand, as with a tool
+    Stored(Item),               // what they had of it is put away
+    Fed,
+}
+
+/// One thing to do on the way to a goal.
+#[derive(Copy, Clone, PartialEq)]
+pub enum Step {
+    Walk(Place),
+    Take(ContainerId, Item),
+    Store(ContainerId, Item),
+    Buy(usize, Item), // at a market
+    Eat,
+}
+
+/// A step someone could take: what has to be true first, what comes of it, and how much
+/// trouble it is, in tiles they'd walk to avoid it. Walking is left to the planner.
+pub struct Action {
+    pub step: Step,
+    pub needs: Vec<Fact>,
+    pub gives: Vec<Fact>,
+    pub spends: Vec<Fact>, // no longer true afterwards
+    pub cost: f32,
+}
+
+impl Action {
+    /// Taking `item` from a container, once there.
+    pub fn take(container_id: ContainerId, item: Item, cost: f32) -> Action {
+        Action {
+            step: Step::Take(container_id, item),
+            needs: vec![
+                Fact::At(Place::Container(container_id)),
+                Fact::Stocked(container_id, item),
+            ],
+            gives: vec![Fact::Carrying(item)],
+            spends: Vec::new(),
+            cost: cost,
+        }
+    }
+
+    /// Taking one `item` to use from a container, once there.
+    pub fn pick_up(container_id: ContainerId, item: Item, cost: f32) -> Action {
+        Action {
+            step: Step::Take(container_id, item),
+            needs: vec![
+                Fact::At(Place::Container(container_id)),
+                Fact::Stocked(container_id, item),
+            ],
+            gives: vec![Fact::Holding(item)],
+            spends: Vec::new(),
+            cost: cost,
+        }
+    }
+
+    /// Putting away what they're carrying of `item` in a container with room, once there.
+    pub fn store(container_id: ContainerId, item: Item, cost: f32) -> Action {
+        Action {
+            step: Step::Store(container_id, item),
+            needs: vec![
+                Fact::At(Place::Container(container_id)),
+                Fact::Carrying(item),
+                Fact::HasSpace(container_id),
+            ],
+            gives: vec![Fact::Stored(item)],
+            spends: vec![Fact::Carrying(item)],
+            cost: cost,
+        }
+    }
+
+    /// Buying `item` at a market, with money brought along.
+    pub fn buy(market_index: usize, item: Item, cost: f32) -> Action {
+        Action {
+            step: Step::Buy(market_index, item),
+            needs: vec![
+                Fact::At(Place::Market(market_index)),
+                Fact::Carrying(Item::MONEY),
+            ],
+            gives: vec![Fact::Carrying(item)],
+            spends: vec![Fact::Carrying(Item::MONEY)],
+            cost: cost,
+        }
+    }
+
+    /// Eating what they've got, wherever they are, or only at home if `at_home`.
+    pub fn eat(at_home: bool, cost: f32) -> Action {
+        let mut needs = vec![Fact::Carrying(Item::FOOD)];
+        if at_home {
+            needs.push(Fact::At(Place::Home));
+        }
+        Action {
+            step: Step::Eat,
+            needs: needs,
+            gives: vec![Fact::Fed],
+            spends: Vec::new(),
+            cost: cost,
+        }
+    }
+}
+
+// a partial plan, cheapest first out of the heap
+struct Node {
+    facts: BTreeSet<Fact>,
+    steps: Vec<Step>,
+    cost: f32,
+}
+
+impl PartialEq for Node {
+    fn eq(&self, other: &Node) -> bool {
+        self.cost == other.cost
+    }
+}
+
+impl Eq for Node {}
+
+impl PartialOrd for Node {
+    fn partial_cmp(&self, other: &Node) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Node {
+    fn cmp(&self, other: &Node) -> Ordering {
+        other
+            .cost
+            .partial_cmp(&self.cost)
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+impl Node {
+    fn location(&self, here: Vector, location: &impl Fn(Place) -> Vector) -> Vector {
+        self.facts
+            .iter()
+            .find_map(|fact| match fact {
+                Fact::At(place) => Some(location(*place)),
+                _ => None,
+            })
+            .unwrap_or(here)
+    }
+
+    fn then(&self, step: Step, spends: &[Fact], gives: &[Fact], cost: f32) -> Node {
+        let mut facts = self.facts.clone();
+        for fact in spends {
+            facts.remove(fact);
+        }
+        facts.extend(gives.iter().cloned());
+        let mut steps = self.steps.clone();
+        steps.push(step);
+        Node {
+            facts: facts,
+            steps: steps,
+            cost: self.cost + cost,
+        }
+    }
+}
+
+/// The cheapest way from `facts` to everything in `goal` being true, walking between places as
+/// need be, or None if there's no way they know of. `here` is where they are now, and `location`
+/// where each place is.
+pub fn plan(
+    facts: &BTreeSet<Fact>,
+    goal: &[Fact],
+    actions: &[Action],
+    here: Vector,
+    location: impl Fn(Place) -> Vector,
+) -> Option<Vec<Step>> {
+    let mut places: Vec<Place> = Vec::new();
+    for action in actions {
+        for fact in &action.needs {
+            if let Fact::At(place) = fact {
+                if !places.contains(place) {
+                    places.push(*place);
+                }
+            }
+        }
+    }
+
+    let mut open = BinaryHeap::new();
+    let mut closed = BTreeSet::new();
+    open.push(Node {
+        facts: facts.clone(),
+        steps: Vec::new(),
+        cost: 0.0,
+    });
+    while let Some(node) = open.pop() {
+        if goal.iter().all(|fact| node.facts.contains(fact)) {
+            return Some(node.steps);
+        }
+        if node.steps.len() >= MAX_STEPS || !closed.insert(node.facts.clone()) {
+            continue;
+        }
+        let from = node.location(here, &location);
+        for &place in &places {
+            if !node.facts.contains(&Fact::At(place)) {
+                let left: Vec<Fact> = node
+                    .facts
+                    .iter()
+                    .filter(|fact| match fact {
+                        Fact::At(_) => true,
+                        _ => false,
+                    })
+                    .cloned()
+                    .collect();
+                let walk = from.distance(location(place));
+                open.push(node.then(Step::Walk(place), &left, &[Fact::At(place)], walk));
+            }
+        }
+        for action in actions {
+            if action.needs.iter().all(|fact| node.facts.contains(fact)) {
+                open.push(node.then(action.step, &action.spends, &action.gives, action.cost));
+            }
+        }
+    }
+    None
+}
+
+impl Save for Place {
+    fn save(&self, writer: &mut SaveWriter) {
+        match self {
+            Place::Home => writer.put("home"),
+            Place::Container(container_id) => {
+                writer.put("container");
+                writer.put(container_id);
+            }
+            Place::Market(market_index) => {
+                writer.put("market");
+                writer.put(market_index);
+            }
+        }
+    }
+
+    fn load(reader: &mut SaveReader) -> Result<Place, SaveError> {
+        let kind: String = reader.get()?;
+        Ok(match kind.as_str() {
+            "home" => Place::Home,
+            "container" => Place::Container(reader.get()?),
+            "market" => Place::Market(reader.get()?),
+            _ => return Err(SaveError::Format(format!("unknown place '{}'", kind))),
+        })
+    }
+}
+
+impl Save for Step {
+    fn save(&self, writer: &mut SaveWriter) {
+        match self {
+            Step::Walk(place) => {
+                writer.put("walk");
+                place.save(writer);
+            }
+            Step::Take(container_id, item) => {
+                writer.put("take");
+                writer.put(container_id);
+                writer.put(item);
+            }
+            Step::Store(container_id, item) => {
+                writer.put("store");
+                writer.put(container_id);
+                writer.put(item);
+            }
+            Step::Buy(market_index, item) => {
+                writer.put("buy");
+                writer.put(market_index);
+                writer.put(item);
+            }
+            Step::Eat => writer.put("eat"),
+        }
+    }
+
+    fn load(reader: &mut SaveReader) -> Result<Step, SaveError> {
+        let kind: String = reader.get()?;
+        Ok(match kind.as_str() {
+            "walk" => Step::Walk(Place::load(reader)?),
+            "take" => Step::Take(reader.get()?, reader.get()?),
+            "store" => Step::Store(reader.get()?, reader.get()?),
+            "buy" => Step::Buy(reader.get()?, reader.get()?),
+            "eat" => Step::Eat,
+            _ => return Err(SaveError::Format(format!("unknown step '{}'", kind))),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // their own store and a neighbour's, each as far from home as the market
+    fn meal_plan(own_food: bool, money: bool) -> Vec<Step> {
+        let own: ContainerId = "0v0".parse().unwrap();
+        let neighbour: ContainerId = "1v0".parse().unwrap();
+        let mut facts = BTreeSet::new();
+        facts.insert(Fact::At(Place::Home));
+        facts.insert(Fact::Stocked(neighbour, Item::FOOD));
+        if own_food {
+            facts.insert(Fact::Stocked(own, Item::FOOD));
+        }
+        if money {
+            facts.insert(Fact::Carrying(Item::MONEY));
+        }
+        let actions = vec![
+            Action::eat(true, 0.0),
+            Action::eat(false, 5.0),
+            Action::take(own, Item::FOOD, 0.0),
+            Action::buy(0, Item::FOOD, 10.0),
+            Action::take(neighbour, Item::FOOD, 20.0),
+        ];
+        let location = |place| match place {
+            Place::Home => Vector::new(0.0, 0.0),
+            Place::Container(container_id) if container_id == own => Vector::new(3.0, 0.0),
+            Place::Container(_) => Vector::new(-3.0, 0.0),
+            Place::Market(_) => Vector::new(0.0, 3.0),
+        };
+        plan(
+            &facts,
+            &[Fact::Fed],
+            &actions,
+            Vector::new(0.0, 0.0),
+            location,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn own_store_before_market() {
+        let steps = meal_plan(true, true);
+        assert!(steps.contains(&Step::Take("0v0".parse().unwrap(), Item::FOOD)));
+        assert!(!steps.iter().any(|step| match step {
+            Step::Buy(_, _) => true,
+            _ => false,
+        }));
+    }
+
+    #[test]
+    fn market_before_neighbour() {
+        let steps = meal_plan(false, true);
+        assert!(steps.contains(&Step::Buy(0, Item::FOOD)));
+        assert!(!steps.contains(&Step::Take("1v0".parse().unwrap(), Item::FOOD)));
+    }
+
+    #[test]
+    fn neighbour_as_a_last_resort() {
+        let steps = meal_plan(false, false);
+        let neighbour = "1v0".parse().unwrap();
+        assert!(
+            steps[..2]
+                == [
+                    Step::Walk(Place::Container(neighbour)),
+                    Step::Take(neighbour, Item::FOOD),
+                ]
+        );
+        assert!(steps.last() == Some(&Step::Eat));
+    }
+
+    // a container next door and another further off
+    fn nearby(place: Place) -> Vector {
+        match place {
+            Place::Container(container_id) if container_id.index() == 0 => Vector::new(1.0, 0.0),
+            Place::Container(_) => Vector::new(10.0, 0.0),
+            _ => Vector::new(0.0, 0.0),
+        }
+    }
+
+    #[test]
+    fn storage_skips_full_container() {
+        let full: ContainerId = "0v0".parse().unwrap();
+        let roomy: ContainerId = "1v0".parse().unwrap();
+        let mut facts = BTreeSet::new();
+        facts.insert(Fact::Carrying(Item::FOOD));
+        facts.insert(Fact::HasSpace(roomy));
+        let actions = vec![
+            Action::store(full, Item::FOOD, 0.0),
+            Action::store(roomy, Item::FOOD, 0.0),
+        ];
+        let goal = [Fact::Stored(Item::FOOD)];
+        let steps = plan(&facts, &goal, &actions, Vector::new(0.0, 0.0), nearby).unwrap();
+        assert!(
+            steps
+                == [
+                    Step::Walk(Place::Container(roomy)),
+                    Step::Store(roomy, Item::FOOD),
+                ]
+        );
+
+        // and with nowhere that has room, there's no plan at all
+        facts.remove(&Fact::HasSpace(roomy));
+        assert!(plan(&facts, &goal, &actions, Vector::new(0.0, 0.0), nearby).is_none());
+    }
+
+    #[test]
+    fn tool_picked_up_where_stocked() {
+        let tool = Item::WOOD; // any item will do
+        let empty: ContainerId = "0v0".parse().unwrap();
+        let stocked: ContainerId = "1v0".parse().unwrap();
+        let mut facts = BTreeSet::new();
+        facts.insert(Fact::Stocked(stocked, tool));
+        let actions = vec![
+            Action::pick_up(empty, tool, 0.0),
+            Action::pick_up(stocked, tool, 0.0),
+        ];
+        let steps = plan(
+            &facts,
+            &[Fact::Holding(tool)],
+            &actions,
+            Vector::new(0.0, 0.0),
+            nearby,
+        )
+        .unwrap();
+        assert!(
+            steps
+                == [
+                    Step::Walk(Place::Container(stocked)),
+                    Step::Take(stocked, tool)
+                ]
+        );
+
+        // carrying some isn't the same as holding one to use
+        let taken = plan(
+            &facts,
+            &[Fact::Carrying(tool)],
+            &actions,
+            Vector::new(0.0, 0.0),
+            nearby,
+        );
+        assert!(taken.is_none());
+    }
+}
@@ -1,36 +1,97 @@
-use std::sync::mpsc::Sender;
+use std::hash::{Hash, Hasher};
 
 use quicksilver::geom::Vector;
 
-use crate::item::{Inventory, Item, ItemMessage};
+use crate::entity::Handle;
+use crate::item::{InventoryId, Item, ItemMessage, Senders};
+use crate::miracle::BLESSING_DAYS;
+use crate::save::{Save, SaveError, SaveReader, SaveWriter};
+use crate::world::World;
+
+pub type CropId = Handle<Crop>;
 
 pub struct Crop {
     pub location: Vector,
-    pub inventory_id: usize,
+    pub inventory_id: InventoryId,
+    blessed_days: u32,
+}
+
+impl Hash for Crop {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.location.x.to_bits().hash(state);
+        self.location.y.to_bits().hash(state);
+        self.inventory_id.hash(state);
+        self.blessed_days.hash(state);
+    }
 }
 
 impl Crop {
-    pub fn new(location: Vector, inventory_id: usize) -> Crop {
+    pub fn new(location: Vector, inventory_id: InventoryId) -> Crop {
         Crop {
             location: location,
             inventory_id: inventory_id,
+            blessed_days: 0,
         }
     }
 
-    pub fn grow(&mut self, sun: u32, rain: u32, senders: &Vec<Sender<ItemMessage>>) {
-        let sender = &senders[self.inventory_id];
+    pub fn bless(&mut self) {
+        self.blessed_days += BLESSING_DAYS;
+    }
+
+    pub fn description_lines(&self, world: &World) -> Vec<String> {
+        let mut lines = vec!["Crop".to_string()];
+        if self.blessed_days > 0 {
+            lines.push(format!("Blessed for {} days", self.blessed_days));
+        }
+        lines.append(&mut world.inventories[self.inventory_id].description_lines(&world.items));
+        lines
+    }
 
-        // self.inventory.do_give_up_to(Item::Water, rain);
-        sender.send(ItemMessage::GiveOrDrop(Item::Water, rain));
+    /// Returns how much food the crop should grow, given the water it already had. Growing
+    /// itself happens through the inventory messages.
+    pub fn grow(&mut self, sun: u32, rain: u32, water: u32, senders: &Senders) -> u32 {
+        // self.inventory.do_give_up_to(Item::WATER, rain);
+        senders.send(
+            self.inventory_id,
+            ItemMessage::GiveOrDrop(Item::WATER, rain),
+        );
 
-        // let growth = self.inventory.do_take_up_to(Item::Water, sun);
-        // self.inventory.do_give_up_to(Item::Food, growth);
+        // let growth = self.inventory.do_take_up_to(Item::WATER, sun);
+        // self.inventory.do_give_up_to(Item::FOOD, growth);
         for _ in 0..sun {
-            sender.send(ItemMessage::Trade(
-                (Item::Food, 1),
-                (Item::Water, 1),
+            senders.send(
                 self.inventory_id,
-            ));
+                ItemMessage::Trade((Item::FOOD, 1), (Item::WATER, 1), self.inventory_id),
+            );
         }
+        let growth = sun.min(water + rain);
+        if self.blessed_days > 0 {
+            // blessed crops grow double, and at least something even on a bad day
+            self.blessed_days -= 1;
+            let bonus = growth.max(1);
+            senders.send(
+                self.inventory_id,
+                ItemMessage::GiveOrDrop(Item::FOOD, bonus),
+            );
+            growth + bonus
+        } else {
+            growth
+        }
+    }
+}
+
+impl Save for Crop {
+    fn save(&self, writer: &mut SaveWriter) {
+        writer.tag("crop");
+        writer.put_vector(self.location);
+        writer.put(self.inventory_id);
+        writer.put(self.blessed_days);
+    }
+
+    fn load(reader: &mut SaveReader) -> Result<Crop, SaveError> {
+        reader.expect("crop")?;
+        let mut crop = Crop::new(reader.get_vector()?, reader.get()?);
+        crop.blessed_days = reader.get()?;
+        Ok(crop)
     }
 }
@@ -0,0 +1,74 @@
+use std::error;
+use std::fmt;
+
+use crate::save::{Save, SaveError, SaveReader, SaveWriter};
+use crate::world::TICKS_PER_DAY;
+
+const POWER_KEPT_PER_DAY: f32 = 0.9;
+
+/// The god's ability to act on the world. It only comes from the devotion of the living, and
+/// fades when they stop paying attention.
+pub struct DivinePower {
+    amount: f32,
+}
+
+#[derive(Debug)]
+pub struct InsufficientPower {
+    pub cost: f32,
+    pub available: f32,
+}
+
+impl fmt::Display for InsufficientPower {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "needs {:.0} divine power but only {:.0} is available",
+            self.cost, self.available
+        )
+    }
+}
+
+impl error::Error for InsufficientPower {}
+
+impl DivinePower {
+    pub fn new(amount: f32) -> DivinePower {
+        DivinePower { amount: amount }
+    }
+
+    pub fn amount(&self) -> f32 {
+        self.amount
+    }
+
+    pub fn can_afford(&self, cost: f32) -> bool {
+        self.amount >= cost
+    }
+
+    pub fn spend(&mut self, cost: f32) -> Result<(), InsufficientPower> {
+        if self.can_afford(cost) {
+            self.amount -= cost;
+            Ok(())
+        } else {
+            Err(InsufficientPower {
+                cost: cost,
+                available: self.amount,
+            })
+        }
+    }
+
+    /// Adds this tick's devotion and lets a tick's worth of the old power fade.
+    pub fn update(&mut self, devotion: f32) {
+        self.amount *= POWER_KEPT_PER_DAY.powf(1.0 / TICKS_PER_DAY as f32);
+        self.amount += devotion;
+    }
+}
+
+impl Save for DivinePower {
+    fn save(&self, writer: &mut SaveWriter) {
+        writer.tag("power");
+        writer.put(self.amount);
+    }
+
+    fn load(reader: &mut SaveReader) -> Result<DivinePower, SaveError> {
+        Ok(DivinePower::new(reader.get_tagged("power")?))
+    }
+}
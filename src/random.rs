@@ -0,0 +1,26 @@
+use rand::rngs::SmallRng;
+use rand::SeedableRng;
+
+// Stream ids keep each subsystem's randomness independent of every other subsystem and of the
+// order rayon happens to schedule entities in. Per-entity streams are offset by entity index.
+pub const WEATHER_STREAM: u64 = 0;
+pub const MIND_STREAM: u64 = 1 << 32;
+pub const HEALTH_STREAM: u64 = 2 << 32;
+pub const FAMILY_STREAM: u64 = 3 << 32;
+pub const SOCIAL_STREAM: u64 = 4 << 32;
+pub const NAME_STREAM: u64 = 5 << 32;
+pub const PERSONALITY_STREAM: u64 = 6 << 32;
+
+// splitmix64 finalizer, so that nearby seeds/streams/ticks give unrelated rng seeds
+fn mix(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Rng for one stream on one tick. Nothing is stored between ticks, so the sequence only depends
+/// on the game seed and the current `Time`.
+pub fn stream_rng(seed: u64, stream: u64, tick: u64) -> SmallRng {
+    SmallRng::seed_from_u64(mix(mix(seed ^ mix(stream)) ^ tick))
+}
@@ -0,0 +1,174 @@
+use std::collections::BTreeMap;
+use std::hash::{Hash, Hasher};
+
+use crate::human::HumanId;
+use crate::save::{Save, SaveError, SaveReader, SaveWriter};
+use crate::world::World;
+
+// lost every day, so people drift apart unless they keep seeing each other
+const FAMILIARITY_FADE: f32 = 0.01;
+const FEELING_FADE: f32 = 0.01; // fraction of trust and affection lost per day
+const SHOWN_RELATIONSHIPS: usize = 3;
+
+/// A way two humans come into contact, each of which brings them a little closer.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Contact {
+    Nearby,    // per tick spent on the same tile
+    Trade,     // per purchase, between buyer and seller
+    Colleague, // per day, working the same place
+    Household, // per day, living together
+    Talk,      // per conversation
+    Argument,  // per conversation that turns into a row
+}
+
+/// What one human makes of another. Familiarity runs from 0 (a stranger) to 1, trust and
+/// affection from -1 to 1, with 0 meaning no opinion either way.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Relationship {
+    pub familiarity: f32,
+    pub trust: f32,
+    pub affection: f32,
+}
+
+/// Everyone a human knows, and what they make of them.
+pub struct Relationships {
+    others: BTreeMap<HumanId, Relationship>,
+}
+
+impl Contact {
+    // (familiarity, trust, affection) gained
+    fn effect(&self) -> (f32, f32, f32) {
+        match self {
+            Contact::Nearby => (0.001, 0.0, 0.0002),
+            Contact::Trade => (0.05, 0.05, 0.0),
+            Contact::Colleague => (0.05, 0.03, 0.02),
+            Contact::Household => (0.1, 0.05, 0.05),
+            Contact::Talk => (0.02, 0.01, 0.01),
+            Contact::Argument => (0.02, -0.02, -0.03),
+        }
+    }
+}
+
+impl Relationship {
+    fn new() -> Relationship {
+        Relationship {
+            familiarity: 0.0,
+            trust: 0.0,
+            affection: 0.0,
+        }
+    }
+}
+
+impl Relationships {
+    pub fn new() -> Relationships {
+        Relationships {
+            others: BTreeMap::new(),
+        }
+    }
+
+    pub fn get(&self, other: HumanId) -> Option<&Relationship> {
+        self.others.get(&other)
+    }
+
+    /// How far they trust `other`, from -1 to 1; 0 for strangers.
+    pub fn trust(&self, other: HumanId) -> f32 {
+        self.get(other)
+            .map_or(0.0, |relationship| relationship.trust)
+    }
+
+    pub fn affection(&self, other: HumanId) -> f32 {
+        self.get(other)
+            .map_or(0.0, |relationship| relationship.affection)
+    }
+
+    pub fn iter<'a>(&'a self) -> impl Iterator<Item = (HumanId, &'a Relationship)> + 'a {
+        self.others
+            .iter()
+            .map(|(&other, relationship)| (other, relationship))
+    }
+
+    pub fn meet(&mut self, other: HumanId, contact: Contact) {
+        let (familiarity, trust, affection) = contact.effect();
+        let relationship = self.others.entry(other).or_insert(Relationship::new());
+        relationship.familiarity = (relationship.familiarity + familiarity).min(1.0);
+        relationship.trust = (relationship.trust + trust).max(-1.0).min(1.0);
+        relationship.affection = (relationship.affection + affection).max(-1.0).min(1.0);
+    }
+
+    pub fn forget(&mut self, other: HumanId) {
+        self.others.remove(&other);
+    }
+
+    /// A day's drifting apart. Anyone they've stopped seeing altogether is forgotten.
+    pub fn fade(&mut self) {
+        for relationship in self.others.values_mut() {
+            relationship.familiarity -= FAMILIARITY_FADE;
+            relationship.trust *= 1.0 - FEELING_FADE;
+            relationship.affection *= 1.0 - FEELING_FADE;
+        }
+        self.others
+            .retain(|_, relationship| relationship.familiarity > 0.0);
+    }
+
+    /// The people they know best.
+    pub fn description_lines(&self, world: &World) -> Vec<String> {
+        let mut lines = vec![format!("Knows {} people", self.others.len())];
+        let mut closest: Vec<(&HumanId, &Relationship)> = self.others.iter().collect();
+        closest.sort_by(|a, b| b.1.familiarity.partial_cmp(&a.1.familiarity).unwrap());
+        for (other, relationship) in closest.into_iter().take(SHOWN_RELATIONSHIPS) {
+            lines.push(format!(
+                "{}: familiarity {:.2}, trust {:+.2}, affection {:+.2}",
+                world
+                    .humans
+                    .get(*other)
+                    .map_or("someone", |human| human.name()),
+                relationship.familiarity,
+                relationship.trust,
+                relationship.affection
+            ));
+        }
+        lines
+    }
+}
+
+impl Hash for Relationships {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for (other, relationship) in &self.others {
+            other.hash(state);
+            relationship.familiarity.to_bits().hash(state);
+            relationship.trust.to_bits().hash(state);
+            relationship.affection.to_bits().hash(state);
+        }
+    }
+}
+
+impl Save for Relationships {
+    fn save(&self, writer: &mut SaveWriter) {
+        writer.tag("relationships");
+        writer.put(self.others.len());
+        for (other, relationship) in &self.others {
+            writer.put(other);
+            writer.put(relationship.familiarity);
+            writer.put(relationship.trust);
+            writer.put(relationship.affection);
+        }
+    }
+
+    fn load(reader: &mut SaveReader) -> Result<Relationships, SaveError> {
+        reader.expect("relationships")?;
+        let mut relationships = Relationships::new();
+        let len: usize = reader.get()?;
+        for _ in 0..len {
+            let other = reader.get()?;
+            relationships.others.insert(
+                other,
+                Relationship {
+                    familiarity: reader.get()?,
+                    trust: reader.get()?,
+                    affection: reader.get()?,
+                },
+            );
+        }
+        Ok(relationships)
+    }
+}
@@ -0,0 +1,195 @@
+use std::error;
+use std::fmt;
+use std::fmt::Display;
+use std::io;
+use std::str::{FromStr, SplitWhitespace};
+
+use quicksilver::geom::Vector;
+
+/// Bumped whenever the layout of any saved type changes. Older saves are rejected rather than
+/// guessed at.
+pub const SAVE_VERSION: u32 = 21;
+pub const SAVE_MAGIC: &str = "esytheism-save";
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(io::Error),
+    Version(u32),
+    Format(String),
+}
+
+impl Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SaveError::Io(err) => write!(f, "io error: {}", err),
+            SaveError::Version(version) => write!(
+                f,
+                "unsupported save version {} (expected {})",
+                version, SAVE_VERSION
+            ),
+            SaveError::Format(message) => write!(f, "malformed save: {}", message),
+        }
+    }
+}
+
+impl SaveError {
+    /// For a handle or index in a save that doesn't lead to anything in it.
+    pub fn dangling<T: Display>(what: &str, reference: T) -> SaveError {
+        SaveError::Format(format!("{} {} doesn't exist", what, reference))
+    }
+}
+
+impl error::Error for SaveError {}
+
+impl From<io::Error> for SaveError {
+    fn from(err: io::Error) -> SaveError {
+        SaveError::Io(err)
+    }
+}
+
+/// Types that can be written to and read back from a save file. Every record starts with a tag
+/// so a reader that gets out of step fails loudly instead of loading garbage.
+pub trait Save: Sized {
+    fn save(&self, writer: &mut SaveWriter);
+    fn load(reader: &mut SaveReader) -> Result<Self, SaveError>;
+}
+
+pub struct SaveWriter {
+    buffer: String,
+}
+
+impl SaveWriter {
+    pub fn new() -> SaveWriter {
+        SaveWriter {
+            buffer: String::new(),
+        }
+    }
+
+    pub fn tag(&mut self, tag: &str) {
+        if !self.buffer.is_empty() {
+            self.buffer.push('\n');
+        }
+        self.buffer.push_str(tag);
+    }
+
+    pub fn put<T: Display>(&mut self, value: T) {
+        self.buffer.push(' ');
+        self.buffer.push_str(&value.to_string());
+    }
+
+    pub fn put_option<T: Display>(&mut self, value: Option<T>) {
+        match value {
+            Some(value) => self.put(value),
+            None => self.put("-"),
+        }
+    }
+
+    pub fn put_vector(&mut self, value: Vector) {
+        self.put(value.x);
+        self.put(value.y);
+    }
+
+    pub fn put_option_vector(&mut self, value: Option<Vector>) {
+        match value {
+            Some(value) => {
+                self.put("at");
+                self.put_vector(value);
+            }
+            None => self.put("-"),
+        }
+    }
+
+    pub fn put_list<T: Save>(&mut self, tag: &str, values: &[T]) {
+        self.tag(tag);
+        self.put(values.len());
+        for value in values {
+            value.save(self);
+        }
+    }
+
+    pub fn finish(mut self) -> String {
+        self.buffer.push('\n');
+        self.buffer
+    }
+}
+
+pub struct SaveReader<'a> {
+    tokens: SplitWhitespace<'a>,
+}
+
+impl<'a> SaveReader<'a> {
+    pub fn new(data: &'a str) -> SaveReader<'a> {
+        SaveReader {
+            tokens: data.split_whitespace(),
+        }
+    }
+
+    fn next_token(&mut self) -> Result<&'a str, SaveError> {
+        self.tokens
+            .next()
+            .ok_or_else(|| SaveError::Format("unexpected end of file".to_string()))
+    }
+
+    pub fn expect(&mut self, tag: &str) -> Result<(), SaveError> {
+        let token = self.next_token()?;
+        if token == tag {
+            Ok(())
+        } else {
+            Err(SaveError::Format(format!(
+                "expected '{}' but found '{}'",
+                tag, token
+            )))
+        }
+    }
+
+    pub fn get<T: FromStr>(&mut self) -> Result<T, SaveError> {
+        let token = self.next_token()?;
+        token
+            .parse()
+            .map_err(|_| SaveError::Format(format!("could not parse '{}'", token)))
+    }
+
+    pub fn get_option<T: FromStr>(&mut self) -> Result<Option<T>, SaveError> {
+        let token = self.next_token()?;
+        if token == "-" {
+            Ok(None)
+        } else {
+            token
+                .parse()
+                .map(Some)
+                .map_err(|_| SaveError::Format(format!("could not parse '{}'", token)))
+        }
+    }
+
+    pub fn get_vector(&mut self) -> Result<Vector, SaveError> {
+        let x: f32 = self.get()?;
+        let y: f32 = self.get()?;
+        Ok(Vector::new(x, y))
+    }
+
+    pub fn get_option_vector(&mut self) -> Result<Option<Vector>, SaveError> {
+        match self.next_token()? {
+            "-" => Ok(None),
+            "at" => Ok(Some(self.get_vector()?)),
+            token => Err(SaveError::Format(format!(
+                "expected a location but found '{}'",
+                token
+            ))),
+        }
+    }
+
+    pub fn get_list<T: Save>(&mut self, tag: &str) -> Result<Vec<T>, SaveError> {
+        self.expect(tag)?;
+        let len: usize = self.get()?;
+        let mut values = Vec::new();
+        for _ in 0..len {
+            values.push(T::load(self)?);
+        }
+        Ok(values)
+    }
+
+    pub fn get_tagged<T: FromStr>(&mut self, tag: &str) -> Result<T, SaveError> {
+        self.expect(tag)?;
+        self.get()
+    }
+}
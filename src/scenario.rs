@@ -0,0 +1,633 @@
+use std::error;
+use std::fmt;
+use std::str::{FromStr, SplitWhitespace};
+
+use quicksilver::geom::Vector;
+
+use crate::catalogue::ItemCatalogue;
+use crate::geography::TilePoint;
+use crate::item::Item;
+use crate::job::Job;
+use crate::plant::CropId;
+use crate::world::Time;
+
+/// Initial village description, loaded from a `.scenario` file. One record per line, `#` starts
+/// a comment:
+///
+/// ```text
+/// map test.map 40 30
+/// seed 42
+/// power <divine power>
+/// shrine <x> <y>
+/// well <x> <y>
+/// bed <x> <y> [capacity <n>]
+/// market <x> <y> [stalls <n>]
+/// start <year> <month> <day> <hour> <minute>
+/// names <name>...
+/// container <name> <x> <y> [capacity <c>] [storage <kind>] [item <Item> <count>]...
+/// crop <name> <x> <y> [capacity <c>] [item <Item> <count>]...
+/// workshop <name> <x> <y> building <kind> [capacity <c>] [item <Item> <count>]...
+/// household <name> <home x> <home y> [owns <container>]...
+/// human <x> <y> job <job> [name <name>] [age <years>] [household <household>] [capacity <c>] [item <Item> <count>]...
+/// ```
+///
+/// where `<job>` is one of `farmer <crop>`, `woodcutter <x> <y>` (where the trees are),
+/// `artisan <workshop> <recipe>` and `merchant <Item>`. Instead of joining a household, a human
+/// can be given one of their own with `[home <x> <y>] [owns <container>]...`.
+///
+/// A bed belongs to the household whose home is on its tile, and sleeps `capacity` of them
+/// (one by default); anyone left over sleeps on the floor.
+///
+/// `names` records, of which there can be several, list the names given to anyone not named in
+/// their own record, and to children born later on. Without any, a stock list is used.
+///
+/// Items, recipes and storage kinds are named as in the `items.catalogue` file next to the
+/// scenario. Containers, crops, workshops and households must be declared before whatever
+/// refers to them. The map path is relative to the scenario file.
+pub struct Scenario {
+    pub map: MapSpec,
+    pub seed: Option<u64>,
+    pub power: f32,
+    pub start: Time,
+    pub shrines: Vec<Vector>,
+    pub wells: Vec<Vector>,
+    pub beds: Vec<BedSpec>,
+    pub markets: Vec<MarketSpec>,
+    pub containers: Vec<ContainerSpec>,
+    pub crops: Vec<CropSpec>,
+    pub workshops: Vec<WorkshopSpec>,
+    pub households: Vec<HouseholdSpec>,
+    pub humans: Vec<HumanSpec>,
+    pub names: Vec<String>,
+}
+
+pub struct MapSpec {
+    pub path: String,
+    pub width: usize,
+    pub height: usize,
+}
+
+pub struct MarketSpec {
+    pub location: Vector,
+    pub stalls: usize,
+}
+
+pub struct BedSpec {
+    pub location: Vector,
+    pub capacity: usize,
+}
+
+pub struct ContainerSpec {
+    pub name: String,
+    pub location: Vector,
+    pub capacity: f32,
+    pub spoilage: f32,
+    pub items: Vec<(Item, u32)>,
+}
+
+pub struct CropSpec {
+    pub name: String,
+    pub location: Vector,
+    pub capacity: f32,
+    pub items: Vec<(Item, u32)>,
+}
+
+pub struct WorkshopSpec {
+    pub name: String,
+    pub location: Vector,
+    pub building: String,
+    pub capacity: f32,
+    pub items: Vec<(Item, u32)>,
+}
+
+pub struct HouseholdSpec {
+    pub name: String, // empty for a household of one, declared with its human
+    pub home: Vector,
+    pub owned_containers: Vec<usize>, // indices into the scenario's containers
+}
+
+pub struct HumanSpec {
+    pub name: Option<String>, // picked from the scenario's names if not given
+    pub location: Vector,
+    pub household: usize, // index into the scenario's households
+    pub job: JobSpec,
+    pub age: u32, // in years
+    pub capacity: f32,
+    pub items: Vec<(Item, u32)>,
+}
+
+/// A job as the scenario gives it. Crops don't exist until the scenario is set up, so farmers
+/// name theirs by its index among the scenario's crops.
+pub enum JobSpec {
+    Farmer(usize),
+    Other(Job),
+}
+
+impl JobSpec {
+    /// `crop_ids` are the crops created for the scenario's, in the same order.
+    pub fn job(self, crop_ids: &[CropId]) -> Job {
+        match self {
+            JobSpec::Farmer(crop_index) => Job::Farmer(crop_ids[crop_index]),
+            JobSpec::Other(job) => job,
+        }
+    }
+}
+
+const DEFAULT_POWER: f32 = 50.0;
+const DEFAULT_STALLS: usize = 4;
+const DEFAULT_BED_CAPACITY: usize = 1;
+const DEFAULT_CONTAINER_CAPACITY: f32 = 1000.0;
+const DEFAULT_CROP_CAPACITY: f32 = 10.0;
+const DEFAULT_WORKSHOP_CAPACITY: f32 = 1000.0;
+const DEFAULT_HUMAN_CAPACITY: f32 = 100.0;
+const DEFAULT_AGE: u32 = 25;
+pub const DEFAULT_NAMES: [&str; 24] = [
+    "Ada", "Bram", "Cora", "Dunstan", "Edda", "Fenn", "Gisla", "Hugh", "Ida", "Jory", "Kestra",
+    "Leof", "Maud", "Nye", "Osric", "Petra", "Quill", "Rowan", "Sigrid", "Tam", "Ulla", "Wat",
+    "Ysolde", "Aldo",
+];
+
+#[derive(Debug)]
+pub struct ScenarioError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ScenarioError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl error::Error for ScenarioError {}
+
+struct Line<'a> {
+    number: usize,
+    tokens: SplitWhitespace<'a>,
+    points: Vec<Vector>, // every location read, to check against the map once it's known
+}
+
+impl<'a> Line<'a> {
+    fn error(&self, message: String) -> ScenarioError {
+        ScenarioError {
+            line: self.number,
+            message: message,
+        }
+    }
+
+    fn word(&mut self, what: &str) -> Result<&'a str, ScenarioError> {
+        let number = self.number;
+        self.tokens.next().ok_or_else(|| ScenarioError {
+            line: number,
+            message: format!("missing {}", what),
+        })
+    }
+
+    fn value<T: FromStr>(&mut self, what: &str) -> Result<T, ScenarioError> {
+        let word = self.word(what)?;
+        word.parse()
+            .map_err(|_| self.error(format!("invalid {} '{}'", what, word)))
+    }
+
+    fn vector(&mut self) -> Result<Vector, ScenarioError> {
+        let x: f32 = self.value("x coordinate")?;
+        let y: f32 = self.value("y coordinate")?;
+        self.points.push(Vector::new(x, y));
+        Ok(Vector::new(x, y))
+    }
+
+    fn item(&mut self, items: &ItemCatalogue) -> Result<Item, ScenarioError> {
+        let name = self.word("item")?;
+        items
+            .find(name)
+            .ok_or_else(|| self.error(format!("unknown item '{}'", name)))
+    }
+
+    fn item_count(&mut self, items: &ItemCatalogue) -> Result<(Item, u32), ScenarioError> {
+        Ok((self.item(items)?, self.value("item count")?))
+    }
+
+    fn container(&mut self, containers: &[ContainerSpec]) -> Result<usize, ScenarioError> {
+        let name = self.word("container name")?;
+        containers
+            .iter()
+            .position(|c| c.name == name)
+            .ok_or_else(|| self.error(format!("unknown container '{}'", name)))
+    }
+}
+
+fn find(names: &[&str], name: &str) -> Option<usize> {
+    names.iter().position(|&n| n == name)
+}
+
+impl Scenario {
+    pub fn parse(data: &str, items: &ItemCatalogue) -> Result<Scenario, ScenarioError> {
+        let mut map = None;
+        let mut seed = None;
+        let mut power = DEFAULT_POWER;
+        let mut start = Time::new();
+        let mut shrines = Vec::new();
+        let mut wells = Vec::new();
+        let mut beds = Vec::new();
+        let mut markets = Vec::new();
+        let mut containers: Vec<ContainerSpec> = Vec::new();
+        let mut crops: Vec<CropSpec> = Vec::new();
+        let mut workshops: Vec<WorkshopSpec> = Vec::new();
+        let mut households: Vec<HouseholdSpec> = Vec::new();
+        let mut humans = Vec::new();
+        let mut names: Vec<String> = Vec::new();
+        let mut points: Vec<(usize, Vector)> = Vec::new(); // with the line they're on
+
+        for (index, text) in data.lines().enumerate() {
+            let text = text.split('#').next().unwrap_or("");
+            let mut line = Line {
+                number: index + 1,
+                tokens: text.split_whitespace(),
+                points: Vec::new(),
+            };
+            let kind = match line.tokens.next() {
+                Some(kind) => kind,
+                None => continue,
+            };
+            match kind {
+                "map" => {
+                    map = Some(MapSpec {
+                        path: line.word("map path")?.to_string(),
+                        width: line.value("map width")?,
+                        height: line.value("map height")?,
+                    });
+                }
+                "seed" => seed = Some(line.value("seed")?),
+                "names" => names.extend(line.tokens.by_ref().map(|name| name.to_string())),
+                "power" => power = line.value("divine power")?,
+                "shrine" => shrines.push(line.vector()?),
+                "well" => wells.push(line.vector()?),
+                "bed" => {
+                    let location = line.vector()?;
+                    let mut capacity = DEFAULT_BED_CAPACITY;
+                    while let Some(key) = line.tokens.next() {
+                        match key {
+                            "capacity" => capacity = line.value("bed capacity")?,
+                            _ => return Err(line.error(format!("unknown bed field '{}'", key))),
+                        }
+                    }
+                    beds.push(BedSpec {
+                        location: location,
+                        capacity: capacity,
+                    });
+                }
+                "market" => {
+                    let location = line.vector()?;
+                    let mut stalls = DEFAULT_STALLS;
+                    while let Some(key) = line.tokens.next() {
+                        match key {
+                            "stalls" => stalls = line.value("stall count")?,
+                            _ => return Err(line.error(format!("unknown market field '{}'", key))),
+                        }
+                    }
+                    markets.push(MarketSpec {
+                        location: location,
+                        stalls: stalls,
+                    });
+                }
+                "start" => {
+                    let year: u16 = line.value("year")?;
+                    let month: u8 = line.value("month")?;
+                    let day: u8 = line.value("day")?;
+                    let hour: u8 = line.value("hour")?;
+                    let minute: u8 = line.value("minute")?;
+                    if year < 1
+                        || month < 1
+                        || month > 12
+                        || day < 1
+                        || day > 30
+                        || hour > 23
+                        || minute > 59
+                    {
+                        return Err(line.error("start date out of range".to_string()));
+                    }
+                    start = Time::at(year - 1, month - 1, day - 1, hour, minute);
+                }
+                "container" | "crop" | "workshop" => {
+                    let name = line.word("name")?.to_string();
+                    let location = line.vector()?;
+                    let mut capacity = match kind {
+                        "crop" => DEFAULT_CROP_CAPACITY,
+                        "workshop" => DEFAULT_WORKSHOP_CAPACITY,
+                        _ => DEFAULT_CONTAINER_CAPACITY,
+                    };
+                    let mut building = None;
+                    let mut spoilage = 1.0;
+                    let mut stock = Vec::new();
+                    while let Some(key) = line.tokens.next() {
+                        match key {
+                            "capacity" => capacity = line.value("capacity")?,
+                            "item" => stock.push(line.item_count(items)?),
+                            "building" if kind == "workshop" => {
+                                building = Some(line.word("building")?.to_string())
+                            }
+                            "storage" if kind == "container" => {
+                                let name = line.word("storage")?;
+                                spoilage = items
+                                    .storage(name)
+                                    .ok_or_else(|| {
+                                        line.error(format!("unknown storage '{}'", name))
+                                    })?
+                                    .spoilage;
+                            }
+                            _ => {
+                                return Err(line.error(format!("unknown {} field '{}'", kind, key)))
+                            }
+                        }
+                    }
+                    if kind == "workshop" {
+                        workshops.push(WorkshopSpec {
+                            name: name,
+                            location: location,
+                            building: building.ok_or_else(|| {
+                                line.error("workshop has no building".to_string())
+                            })?,
+                            capacity: capacity,
+                            items: stock,
+                        });
+                    } else if kind == "crop" {
+                        crops.push(CropSpec {
+                            name: name,
+                            location: location,
+                            capacity: capacity,
+                            items: stock,
+                        });
+                    } else {
+                        containers.push(ContainerSpec {
+                            name: name,
+                            location: location,
+                            capacity: capacity,
+                            spoilage: spoilage,
+                            items: stock,
+                        });
+                    }
+                }
+                "household" => {
+                    let name = line.word("household name")?.to_string();
+                    let home = line.vector()?;
+                    let mut owned_containers = Vec::new();
+                    while let Some(key) = line.tokens.next() {
+                        match key {
+                            "owns" => owned_containers.push(line.container(&containers)?),
+                            _ => {
+                                return Err(line.error(format!("unknown household field '{}'", key)))
+                            }
+                        }
+                    }
+                    households.push(HouseholdSpec {
+                        name: name,
+                        home: home,
+                        owned_containers: owned_containers,
+                    });
+                }
+                "human" => {
+                    let location = line.vector()?;
+                    let mut name = None;
+                    let mut household = None;
+                    let mut home = None;
+                    let mut job = None;
+                    let mut age = DEFAULT_AGE;
+                    let mut owned_containers = Vec::new();
+                    let mut capacity = DEFAULT_HUMAN_CAPACITY;
+                    let mut stock = Vec::new();
+                    let crop_names: Vec<&str> = crops.iter().map(|c| c.name.as_str()).collect();
+                    while let Some(key) = line.tokens.next() {
+                        match key {
+                            "job" => match line.word("job")? {
+                                "farmer" => {
+                                    let crop = line.word("crop name")?;
+                                    let crop_index = find(&crop_names, crop).ok_or_else(|| {
+                                        line.error(format!("unknown crop '{}'", crop))
+                                    })?;
+                                    job = Some(JobSpec::Farmer(crop_index));
+                                }
+                                "woodcutter" => {
+                                    let trees = line.vector()?;
+                                    job = Some(JobSpec::Other(Job::Woodcutter(
+                                        TilePoint::from_vector(&trees),
+                                    )));
+                                }
+                                "artisan" => {
+                                    let workshop = line.word("workshop name")?;
+                                    let workshop_index = workshops
+                                        .iter()
+                                        .position(|w| w.name == workshop)
+                                        .ok_or_else(|| {
+                                            line.error(format!("unknown workshop '{}'", workshop))
+                                        })?;
+                                    let name = line.word("recipe")?;
+                                    let recipe_index =
+                                        items.find_recipe(name).ok_or_else(|| {
+                                            line.error(format!("unknown recipe '{}'", name))
+                                        })?;
+                                    let building = &workshops[workshop_index].building;
+                                    match &items.recipe(recipe_index).building {
+                                        Some(needed) if needed != building => {
+                                            return Err(line.error(format!(
+                                                "recipe '{}' needs a {}, not a {}",
+                                                name, needed, building
+                                            )))
+                                        }
+                                        _ => (),
+                                    }
+                                    job = Some(JobSpec::Other(Job::Artisan(
+                                        workshop_index,
+                                        recipe_index,
+                                    )));
+                                }
+                                "merchant" => {
+                                    job = Some(JobSpec::Other(Job::Merchant(line.item(items)?)))
+                                }
+                                other => return Err(line.error(format!("unknown job '{}'", other))),
+                            },
+                            "name" => name = Some(line.word("name")?.to_string()),
+                            "age" => age = line.value("age")?,
+                            "household" => {
+                                let name = line.word("household name")?;
+                                household = Some(
+                                    households.iter().position(|h| h.name == name).ok_or_else(
+                                        || line.error(format!("unknown household '{}'", name)),
+                                    )?,
+                                );
+                            }
+                            "home" => home = Some(line.vector()?),
+                            "owns" => owned_containers.push(line.container(&containers)?),
+                            "capacity" => capacity = line.value("capacity")?,
+                            "item" => stock.push(line.item_count(items)?),
+                            _ => return Err(line.error(format!("unknown human field '{}'", key))),
+                        }
+                    }
+                    let household = match household {
+                        Some(household) => {
+                            if home.is_some() || !owned_containers.is_empty() {
+                                return Err(line.error(
+                                    "a household's home and containers are given with the household"
+                                        .to_string(),
+                                ));
+                            }
+                            household
+                        }
+                        None => {
+                            households.push(HouseholdSpec {
+                                name: String::new(),
+                                home: home.unwrap_or(location),
+                                owned_containers: owned_containers,
+                            });
+                            households.len() - 1
+                        }
+                    };
+                    humans.push(HumanSpec {
+                        name: name,
+                        location: location,
+                        household: household,
+                        job: job.ok_or_else(|| line.error("human has no job".to_string()))?,
+                        age: age,
+                        capacity: capacity,
+                        items: stock,
+                    });
+                }
+                _ => return Err(line.error(format!("unknown record '{}'", kind))),
+            }
+            let number = line.number;
+            points.extend(line.points.into_iter().map(|point| (number, point)));
+        }
+
+        let map = map.ok_or_else(|| ScenarioError {
+            line: 0,
+            message: "no map given".to_string(),
+        })?;
+        for (number, point) in points {
+            let on_map = point.x >= 0.0
+                && point.y >= 0.0
+                && point.x < map.width as f32
+                && point.y < map.height as f32;
+            if !on_map {
+                return Err(ScenarioError {
+                    line: number,
+                    message: format!(
+                        "({}, {}) is off the {}x{} map",
+                        point.x, point.y, map.width, map.height
+                    ),
+                });
+            }
+        }
+
+        Ok(Scenario {
+            map: map,
+            seed: seed,
+            power: power,
+            start: start,
+            shrines: shrines,
+            wells: wells,
+            beds: beds,
+            markets: markets,
+            containers: containers,
+            crops: crops,
+            workshops: workshops,
+            households: households,
+            humans: humans,
+            names: if names.is_empty() {
+                DEFAULT_NAMES.iter().map(|name| name.to_string()).collect()
+            } else {
+                names
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(data: &str) -> Result<Scenario, ScenarioError> {
+        let items = ItemCatalogue::parse(include_str!("../static/data/items.catalogue")).unwrap();
+        Scenario::parse(data, &items)
+    }
+
+    fn error(data: &str) -> ScenarioError {
+        match parse(data) {
+            Ok(_) => panic!("scenario should have been rejected"),
+            Err(err) => err,
+        }
+    }
+
+    #[test]
+    fn shipped_scenario_parses() {
+        let scenario = parse(include_str!("../static/data/village.scenario")).unwrap();
+        assert_eq!(scenario.map.path, "test.map");
+        assert_eq!((scenario.map.width, scenario.map.height), (40, 30));
+        assert!(!scenario.humans.is_empty());
+    }
+
+    #[test]
+    fn records_are_read() {
+        let scenario = parse(
+            "map test.map 40 30 # comments are skipped\n\
+             \n\
+             seed 7\n\
+             well 3.5 4.5\n\
+             container box 5.5 5.5 capacity 20 item Food 10\n\
+             crop field 6.5 5.5\n\
+             human 5.5 6.5 job farmer field name Ada age 30 owns box item Money 5\n",
+        )
+        .unwrap();
+        assert_eq!(scenario.seed, Some(7));
+        assert!(scenario.wells == vec![Vector::new(3.5, 4.5)]);
+        assert_eq!(scenario.containers[0].name, "box");
+        assert_eq!(scenario.containers[0].capacity, 20.0);
+        assert!(scenario.containers[0].items == vec![(Item::FOOD, 10)]);
+        assert_eq!(scenario.crops[0].capacity, DEFAULT_CROP_CAPACITY);
+        let human = &scenario.humans[0];
+        assert_eq!(human.name, Some("Ada".to_string()));
+        assert_eq!(human.age, 30);
+        match human.job {
+            JobSpec::Farmer(crop_index) => assert_eq!(crop_index, 0),
+            JobSpec::Other(_) => panic!("should be a farmer"),
+        }
+        let household = &scenario.households[human.household];
+        assert_eq!(household.owned_containers, vec![0]);
+    }
+
+    #[test]
+    fn unknown_record_rejected() {
+        let err = error("map test.map 40 30\nfountain 3 4\n");
+        assert_eq!(err.line, 2);
+        assert_eq!(err.message, "unknown record 'fountain'");
+    }
+
+    #[test]
+    fn missing_field_rejected() {
+        let err = error("map test.map 40 30\nwell 3\n");
+        assert_eq!(err.line, 2);
+        assert_eq!(err.message, "missing y coordinate");
+
+        let err = error("well 3 4\n");
+        assert_eq!(err.line, 0);
+        assert_eq!(err.message, "no map given");
+    }
+
+    #[test]
+    fn invalid_field_rejected() {
+        let err = error("map test.map 40 30\nwell 3 four\n");
+        assert_eq!(err.line, 2);
+        assert_eq!(err.message, "invalid y coordinate 'four'");
+
+        let err = error("map test.map 40 30\ncontainer box 3 4 item Gold 1\n");
+        assert_eq!(err.line, 2);
+    }
+
+    #[test]
+    fn location_off_the_map_rejected() {
+        let err = error("map test.map 40 30\nwell 3 4\nhuman 40.5 4 job merchant Food\n");
+        assert_eq!(err.line, 3);
+        assert_eq!(err.message, "(40.5, 4) is off the 40x30 map");
+
+        let err = error("well 3 -1\nmap test.map 40 30\n");
+        assert_eq!(err.line, 1);
+    }
+}
@@ -0,0 +1,131 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::catalogue::{ItemCatalogue, CATALOGUE_FILE};
+use crate::gamestate::GameState;
+use crate::geography::{Geography, MapError, MAP_HEIGHT, MAP_WIDTH};
+use crate::save::SaveError;
+use crate::scenario::Scenario;
+
+/// Drives a `GameState` without a window. Each step is one simulation tick followed by a full
+/// tick's worth of travel, which is what `Engine` does over `updates_per_tick` frames.
+pub struct Simulation {
+    pub game_state: GameState,
+    ticks: u64,
+}
+
+impl Simulation {
+    pub fn new(game_state: GameState) -> Simulation {
+        Simulation {
+            game_state: game_state,
+            ticks: 0,
+        }
+    }
+
+    pub fn from_map_data(
+        data: &[u8],
+        items: ItemCatalogue,
+        seed: u64,
+    ) -> Result<Simulation, MapError> {
+        Ok(Simulation::new(GameState::new(
+            Geography::from_data(MAP_WIDTH, MAP_HEIGHT, data)?,
+            items,
+            seed,
+        )))
+    }
+
+    /// Loads a map with the item catalogue next to it.
+    pub fn from_map_path<P: AsRef<Path>>(path: P, seed: u64) -> io::Result<Simulation> {
+        let path = path.as_ref();
+        let items = load_catalogue(path)?;
+        let data = fs::read(path)?;
+        Simulation::from_map_data(&data, items, seed)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    /// Loads a scenario file, the item catalogue next to it and the map it refers to, which is
+    /// resolved relative to the scenario file.
+    pub fn from_scenario_path<P: AsRef<Path>>(
+        path: P,
+        seed: Option<u64>,
+    ) -> io::Result<Simulation> {
+        let path = path.as_ref();
+        let items = load_catalogue(path)?;
+        let scenario = Scenario::parse(&fs::read_to_string(path)?, &items)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        let map_path = path
+            .parent()
+            .unwrap_or(Path::new(""))
+            .join(&scenario.map.path);
+        let geo = Geography::from_data(
+            scenario.map.width,
+            scenario.map.height,
+            &fs::read(map_path)?,
+        )
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        Ok(Simulation::new(GameState::from_scenario(
+            geo, items, scenario, seed,
+        )))
+    }
+
+    pub fn from_save_path<P: AsRef<Path>>(path: P) -> Result<Simulation, SaveError> {
+        Ok(Simulation::new(GameState::load_from_path(path)?))
+    }
+
+    pub fn step(&mut self) {
+        self.game_state.update();
+        self.game_state.do_travel(1);
+        self.ticks += 1;
+    }
+
+    pub fn run(&mut self, ticks: u64) {
+        for _ in 0..ticks {
+            self.step();
+        }
+    }
+
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+}
+
+// the catalogue in the same folder as `path`
+fn load_catalogue(path: &Path) -> io::Result<ItemCatalogue> {
+    let catalogue_path = path.parent().unwrap_or(Path::new("")).join(CATALOGUE_FILE);
+    ItemCatalogue::parse(&fs::read_to_string(catalogue_path)?)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VILLAGE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/static/data/village.scenario");
+
+    fn village(seed: u64, ticks: u64) -> Simulation {
+        let mut sim = Simulation::from_scenario_path(VILLAGE, Some(seed)).unwrap();
+        sim.run(ticks);
+        sim
+    }
+
+    #[test]
+    fn same_seed_same_village() {
+        let a = village(3, 500);
+        let b = village(3, 500);
+        assert_eq!(
+            a.game_state.world.fingerprint(),
+            b.game_state.world.fingerprint()
+        );
+    }
+
+    #[test]
+    fn different_seed_different_village() {
+        let a = village(3, 500);
+        let b = village(4, 500);
+        assert_ne!(
+            a.game_state.world.fingerprint(),
+            b.game_state.world.fingerprint()
+        );
+    }
+}
@@ -0,0 +1,59 @@
+use crate::catalogue::{ItemCatalogue, ItemTag};
+use crate::human::Human;
+use crate::item::Item;
+use crate::world::World;
+
+const INPUT_WORTH: f32 = 2.0; // materials are worth at least this much to whoever works them
+const PRODUCER_DISCOUNT: f32 = 0.75; // what you make yourself is easier to come by
+
+/// How much one more unit of an item is worth to this human, in money. Each item has a worth to
+/// someone who owns none of it, from the catalogue but depending on their job and needs, which
+/// halves for every "half stock" they already own.
+pub fn marginal_value(human: &Human, item: Item, world: &World) -> f32 {
+    let owned = human.owned_item_count(item, world);
+    worth(human, item, &world.items) * 0.5f32.powf(owned as f32 / half_stock(item, &world.items))
+}
+
+/// The most of an item this human wants to own when it sells for `price`: buying beyond it, or
+/// keeping beyond it rather than selling, isn't worth the money.
+pub fn wanted_stock(human: &Human, item: Item, price: f32, items: &ItemCatalogue) -> u32 {
+    let worth = worth(human, item, items);
+    if worth <= price {
+        0
+    } else {
+        (half_stock(item, items) * (worth / price).log2()).floor() as u32
+    }
+}
+
+fn worth(human: &Human, item: Item, items: &ItemCatalogue) -> f32 {
+    let definition = items.get(item);
+    let worth = if definition.has_tag(ItemTag::Currency) {
+        1.0
+    } else if definition.has_tag(ItemTag::Edible) {
+        // the hungrier they are, the more the next meal matters
+        definition.worth * (1.0 + human.hunger / 100.0)
+    } else if definition.has_tag(ItemTag::Drinkable) {
+        definition.worth * (1.0 + human.thirst / 100.0)
+    } else {
+        definition.worth
+    };
+    let worth = if human.job().inputs(items).contains(&item) {
+        worth.max(INPUT_WORTH)
+    } else {
+        worth
+    };
+    if !definition.has_tag(ItemTag::Currency) && human.job().product(items) == item {
+        worth * PRODUCER_DISCOUNT
+    } else {
+        worth
+    }
+}
+
+fn half_stock(item: Item, items: &ItemCatalogue) -> f32 {
+    let definition = items.get(item);
+    if definition.has_tag(ItemTag::Currency) {
+        std::f32::INFINITY // money never loses its worth
+    } else {
+        definition.half_stock
+    }
+}
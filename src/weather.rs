@@ -1,6 +1,10 @@
 use rand::distributions::{Distribution, Normal};
 use rand::prelude::*;
 
+use std::hash::{Hash, Hasher};
+
+use crate::save::{Save, SaveError, SaveReader, SaveWriter};
+
 pub struct Weather {
     current: f64,
     sun: u32,
@@ -16,10 +20,20 @@ impl Weather {
         }
     }
 
-    pub fn update(&mut self) {
-        let mut rng = thread_rng();
+    pub fn update<R: Rng>(&mut self, rng: &mut R) {
         let normal = Normal::new(0.0, 10.0);
-        self.current = ((self.current * 2.0) + normal.sample(&mut rng)) / 3.0;
+        self.current = ((self.current * 2.0) + normal.sample(rng)) / 3.0;
+        self.settle();
+    }
+
+    /// Overrides today's weather. Positive is sunny, negative is rainy, and tomorrow's weather
+    /// drifts on from here as usual.
+    pub fn force(&mut self, current: f64) {
+        self.current = current;
+        self.settle();
+    }
+
+    fn settle(&mut self) {
         if self.current > 0.0 {
             self.sun = (self.current / 10.0) as u32;
             self.rain = 0;
@@ -37,3 +51,29 @@ impl Weather {
         self.rain
     }
 }
+
+impl Hash for Weather {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.current.to_bits().hash(state);
+        self.sun.hash(state);
+        self.rain.hash(state);
+    }
+}
+
+impl Save for Weather {
+    fn save(&self, writer: &mut SaveWriter) {
+        writer.tag("weather");
+        writer.put(self.current);
+        writer.put(self.sun);
+        writer.put(self.rain);
+    }
+
+    fn load(reader: &mut SaveReader) -> Result<Weather, SaveError> {
+        reader.expect("weather")?;
+        Ok(Weather {
+            current: reader.get()?,
+            sun: reader.get()?,
+            rain: reader.get()?,
+        })
+    }
+}
@@ -1,28 +1,273 @@
 use quicksilver::geom::Vector;
 
-use crate::geography::Geography;
-use crate::human::Human;
-use crate::item::Inventory;
+use std::cmp::Ordering;
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::num::ParseIntError;
+use std::str::FromStr;
+
+use crate::catalogue::ItemCatalogue;
+use crate::entity::{Arena, Handle};
+use crate::event::EventRecord;
+use crate::geography::{Geography, TilePoint};
+use crate::household::{Household, HouseholdId};
+use crate::human::{Human, HumanId};
+use crate::item::{Inventory, InventoryId};
+use crate::market::Market;
 use crate::plant::Crop;
+use crate::save::{Save, SaveError, SaveReader, SaveWriter};
 use crate::weather::Weather;
 
 pub const TICKS_PER_MINUTE: u8 = 1;
+pub const TICKS_PER_DAY: u64 = TICKS_PER_MINUTE as u64 * 60 * 24;
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
 
 pub struct World {
     pub geography: Geography,
-    pub humans: Vec<Human>,
-    pub containers: Vec<Container>,
+    pub items: ItemCatalogue,
+    pub humans: Arena<Human>,
+    pub households: Arena<Household>,
+    pub containers: Arena<Container>,
     pub time: Time,
     pub weather: Weather,
-    pub crops: Vec<Crop>,
-    pub inventories: Vec<Inventory>,
+    pub crops: Arena<Crop>,
+    pub inventories: Arena<Inventory>,
+    pub events: Vec<EventRecord>, // the last day's worth
+    pub shrines: Vec<Shrine>,
+    pub markets: Vec<Market>,
+    pub workshops: Vec<Workshop>,
+    pub wells: Vec<Well>,
+    pub beds: Vec<Bed>,
 }
 
+pub type ContainerId = Handle<Container>;
+
 pub struct Container {
     pub location: Vector,
-    pub inventory_id: usize,
+    pub inventory_id: InventoryId,
+}
+
+/// A building where materials are worked into goods. It keeps its own stock of materials, which
+/// recipes worked there use up.
+pub struct Workshop {
+    pub location: Vector,
+    pub building: String, // what kind of building it is, for recipes needing one
+    pub inventory_id: InventoryId,
 }
 
+impl World {
+    /// Where whatever holds this inventory is standing.
+    pub fn inventory_location(&self, inventory_id: InventoryId) -> Option<Vector> {
+        self.humans
+            .values()
+            .map(|human| (human.inventory_id, human.location))
+            .chain(
+                self.containers
+                    .values()
+                    .map(|container| (container.inventory_id, container.location)),
+            )
+            .chain(
+                self.crops
+                    .values()
+                    .map(|crop| (crop.inventory_id, crop.location)),
+            )
+            .find(|(id, _)| *id == inventory_id)
+            .map(|(_, location)| location)
+    }
+
+    pub fn nearest_market(&self, location: Vector) -> Option<usize> {
+        self.markets
+            .iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| {
+                location
+                    .distance(a.location)
+                    .partial_cmp(&location.distance(b.location))
+                    .unwrap_or(Ordering::Equal)
+            })
+            .map(|(index, _)| index)
+    }
+
+    /// The human who carries `inventory_id` about, if it's anyone's.
+    pub fn carrier(&self, inventory_id: InventoryId) -> Option<HumanId> {
+        self.humans
+            .iter()
+            .find(|(_, human)| human.inventory_id == inventory_id)
+            .map(|(human_id, _)| human_id)
+    }
+
+    /// The household that owns `container_id`, if any does.
+    pub fn container_owner(&self, container_id: ContainerId) -> Option<HouseholdId> {
+        self.households
+            .iter()
+            .find(|(_, household)| household.containers().contains(&container_id))
+            .map(|(household_id, _)| household_id)
+    }
+
+    /// Someone's place in one of the beds at home, if there's room for them. Beds are shared out
+    /// among the household in the order they joined it.
+    pub fn bed(&self, human_id: HumanId) -> Option<&Bed> {
+        let household = &self.households[self.humans[human_id].household()];
+        let mut place = household
+            .members()
+            .iter()
+            .position(|&member| member == human_id)?;
+        let home = TilePoint::from_vector(&household.home);
+        self.beds
+            .iter()
+            .filter(|bed| TilePoint::from_vector(&bed.location) == home)
+            .find(|bed| {
+                if place < bed.capacity {
+                    true
+                } else {
+                    place -= bed.capacity;
+                    false
+                }
+            })
+    }
+
+    /// The closest place to draw water: a well, or the middle of a water tile.
+    pub fn nearest_water(&self, location: Vector) -> Option<Vector> {
+        self.wells
+            .iter()
+            .map(|well| well.location)
+            .chain(
+                self.geography
+                    .water_tiles()
+                    .iter()
+                    .map(|tile| Vector::new(tile.x as f32 + 0.5, tile.y as f32 + 0.5)),
+            )
+            .min_by(|a, b| {
+                location
+                    .distance(*a)
+                    .partial_cmp(&location.distance(*b))
+                    .unwrap_or(Ordering::Equal)
+            })
+    }
+
+    /// Makes sure the handles and indices entities keep to each other all lead somewhere, so a
+    /// damaged save is turned away when it's loaded instead of panicking later on.
+    pub fn check_references(&self) -> Result<(), SaveError> {
+        let inventory_ids = self
+            .containers
+            .values()
+            .map(|container| container.inventory_id)
+            .chain(self.crops.values().map(|crop| crop.inventory_id))
+            .chain(self.workshops.iter().map(|workshop| workshop.inventory_id))
+            .chain(
+                self.markets
+                    .iter()
+                    .flat_map(|market| market.stalls.iter().map(|stall| stall.inventory_id)),
+            )
+            .chain(self.humans.values().map(|human| human.inventory_id));
+        for inventory_id in inventory_ids {
+            if !self.inventories.contains(inventory_id) {
+                return Err(SaveError::dangling("inventory", inventory_id));
+            }
+        }
+        for household in self.households.values() {
+            for &container_id in household.containers() {
+                if !self.containers.contains(container_id) {
+                    return Err(SaveError::dangling("container", container_id));
+                }
+            }
+            for &member in household.members() {
+                if !self.humans.contains(member) {
+                    return Err(SaveError::dangling("human", member));
+                }
+            }
+        }
+        for human in self.humans.values() {
+            if !self.households.contains(human.household()) {
+                return Err(SaveError::dangling("household", human.household()));
+            }
+            if let Some(partner) = human.partner() {
+                if !self.humans.contains(partner) {
+                    return Err(SaveError::dangling("human", partner));
+                }
+            }
+            human.job().check_references(self)?;
+        }
+        Ok(())
+    }
+
+    /// Hash of all simulated state (geography and the item catalogue are static and left out). Two runs from the same
+    /// seed and map should agree on this at every tick.
+    pub fn fingerprint(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.humans.hash(&mut hasher);
+        self.households.hash(&mut hasher);
+        self.containers.hash(&mut hasher);
+        self.time.hash(&mut hasher);
+        self.weather.hash(&mut hasher);
+        self.crops.hash(&mut hasher);
+        self.inventories.hash(&mut hasher);
+        self.events.hash(&mut hasher);
+        self.markets.hash(&mut hasher);
+        self.workshops.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+impl Container {
+    pub fn description_lines(&self, world: &World) -> Vec<String> {
+        let mut lines = vec!["Container".to_string()];
+        lines.append(&mut world.inventories[self.inventory_id].description_lines(&world.items));
+        lines
+    }
+}
+
+impl Hash for Container {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.location.x.to_bits().hash(state);
+        self.location.y.to_bits().hash(state);
+        self.inventory_id.hash(state);
+    }
+}
+
+impl Workshop {
+    pub fn description_lines(&self, world: &World) -> Vec<String> {
+        let mut lines = vec![format!("Workshop ({})", self.building)];
+        lines.append(&mut world.inventories[self.inventory_id].description_lines(&world.items));
+        lines
+    }
+}
+
+impl Hash for Workshop {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.location.x.to_bits().hash(state);
+        self.location.y.to_bits().hash(state);
+        self.building.hash(state);
+        self.inventory_id.hash(state);
+    }
+}
+
+/// A holy place. Prayers said here count for more.
+pub struct Shrine {
+    pub location: Vector,
+}
+
+/// Where anyone can draw water, like they can from a river.
+pub struct Well {
+    pub location: Vector,
+}
+
+/// Somewhere to sleep that's better than the floor, for as many as it can take. A bed belongs to
+/// whichever household's home it's in.
+pub struct Bed {
+    pub location: Vector,
+    pub capacity: usize,
+}
+
+/// A calendar day, counted from the first day of year zero. Days before that, like the
+/// birthdays of anyone already grown when the game starts, are negative.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Day(pub i64);
+
+#[derive(Hash)]
 pub struct Time {
     pub tick: u8,
     pub minute: u8,
@@ -46,6 +291,21 @@ impl Time {
         }
     }
 
+    /// Arguments are zero-based, like the fields. The weekday follows from the date, with day
+    /// zero of year zero being a Monday.
+    pub fn at(year: u16, month: u8, day: u8, hour: u8, minute: u8) -> Time {
+        let days = (year as u64 * 12 + month as u64) * 30 + day as u64;
+        Time {
+            tick: 0,
+            minute: minute,
+            hour: hour,
+            day: day,
+            weekday: (days % 7) as u8,
+            month: month,
+            year: year,
+        }
+    }
+
     pub fn tick(&mut self) {
         self.tick += 1;
         if self.tick % TICKS_PER_MINUTE == 0 {
@@ -72,8 +332,18 @@ impl Time {
         }
     }
 
+    pub fn ticks_elapsed(&self) -> u64 {
+        let days = (self.year as u64 * 12 + self.month as u64) * 30 + self.day as u64;
+        ((days * 24 + self.hour as u64) * 60 + self.minute as u64) * TICKS_PER_MINUTE as u64
+            + self.tick as u64
+    }
+
+    pub fn today(&self) -> Day {
+        Day((self.ticks_elapsed() / TICKS_PER_DAY) as i64)
+    }
+
     pub fn is_new_day(&self) -> bool {
-        self.tick == 0 && self.hour == 0
+        self.tick == 0 && self.minute == 0 && self.hour == 0
     }
 
     pub fn date_string(&self) -> String {
@@ -88,20 +358,7 @@ impl Time {
                 5 => "Sat",
                 _ => "Sun",
             },
-            month = match &self.month {
-                0 => "Jan",
-                1 => "Feb",
-                2 => "Mar",
-                3 => "Apr",
-                4 => "May",
-                5 => "Jun",
-                6 => "Jul",
-                7 => "Aug",
-                8 => "Sep",
-                9 => "Oct",
-                10 => "Nov",
-                _ => "Dec",
-            },
+            month = MONTHS[self.month as usize % 12],
             day = self.day + 1,
             year = self.year + 1,
             hour = self.hour,
@@ -109,3 +366,173 @@ impl Time {
         )
     }
 }
+
+impl Day {
+    pub fn date_string(&self) -> String {
+        let month = self.0.div_euclid(30).rem_euclid(12);
+        format!(
+            "{month} {day:02}, {year:04}",
+            month = MONTHS[month as usize],
+            day = self.0.rem_euclid(30) + 1,
+            year = self.0.div_euclid(360) + 1,
+        )
+    }
+}
+
+impl fmt::Display for Day {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl FromStr for Day {
+    type Err = ParseIntError;
+
+    fn from_str(s: &str) -> Result<Day, ParseIntError> {
+        s.parse().map(Day)
+    }
+}
+
+impl Save for Time {
+    fn save(&self, writer: &mut SaveWriter) {
+        writer.tag("time");
+        writer.put(self.tick);
+        writer.put(self.minute);
+        writer.put(self.hour);
+        writer.put(self.day);
+        writer.put(self.weekday);
+        writer.put(self.month);
+        writer.put(self.year);
+    }
+
+    fn load(reader: &mut SaveReader) -> Result<Time, SaveError> {
+        reader.expect("time")?;
+        Ok(Time {
+            tick: reader.get()?,
+            minute: reader.get()?,
+            hour: reader.get()?,
+            day: reader.get()?,
+            weekday: reader.get()?,
+            month: reader.get()?,
+            year: reader.get()?,
+        })
+    }
+}
+
+impl Save for Container {
+    fn save(&self, writer: &mut SaveWriter) {
+        writer.tag("container");
+        writer.put_vector(self.location);
+        writer.put(self.inventory_id);
+    }
+
+    fn load(reader: &mut SaveReader) -> Result<Container, SaveError> {
+        reader.expect("container")?;
+        Ok(Container {
+            location: reader.get_vector()?,
+            inventory_id: reader.get()?,
+        })
+    }
+}
+
+impl Save for Workshop {
+    fn save(&self, writer: &mut SaveWriter) {
+        writer.tag("workshop");
+        writer.put_vector(self.location);
+        writer.put(&self.building);
+        writer.put(self.inventory_id);
+    }
+
+    fn load(reader: &mut SaveReader) -> Result<Workshop, SaveError> {
+        reader.expect("workshop")?;
+        Ok(Workshop {
+            location: reader.get_vector()?,
+            building: reader.get()?,
+            inventory_id: reader.get()?,
+        })
+    }
+}
+
+impl Save for Well {
+    fn save(&self, writer: &mut SaveWriter) {
+        writer.tag("well");
+        writer.put_vector(self.location);
+    }
+
+    fn load(reader: &mut SaveReader) -> Result<Well, SaveError> {
+        reader.expect("well")?;
+        Ok(Well {
+            location: reader.get_vector()?,
+        })
+    }
+}
+
+impl Save for Bed {
+    fn save(&self, writer: &mut SaveWriter) {
+        writer.tag("bed");
+        writer.put_vector(self.location);
+        writer.put(self.capacity);
+    }
+
+    fn load(reader: &mut SaveReader) -> Result<Bed, SaveError> {
+        reader.expect("bed")?;
+        Ok(Bed {
+            location: reader.get_vector()?,
+            capacity: reader.get()?,
+        })
+    }
+}
+
+impl Save for Shrine {
+    fn save(&self, writer: &mut SaveWriter) {
+        writer.tag("shrine");
+        writer.put_vector(self.location);
+    }
+
+    fn load(reader: &mut SaveReader) -> Result<Shrine, SaveError> {
+        reader.expect("shrine")?;
+        Ok(Shrine {
+            location: reader.get_vector()?,
+        })
+    }
+}
+
+impl Save for World {
+    fn save(&self, writer: &mut SaveWriter) {
+        self.geography.save(writer);
+        self.items.save(writer);
+        self.time.save(writer);
+        self.weather.save(writer);
+        self.inventories.save("inventories", writer);
+        self.containers.save("containers", writer);
+        self.crops.save("crops", writer);
+        self.humans.save("humans", writer);
+        self.households.save("households", writer);
+        writer.put_list("events", &self.events);
+        writer.put_list("shrines", &self.shrines);
+        writer.put_list("markets", &self.markets);
+        writer.put_list("workshops", &self.workshops);
+        writer.put_list("wells", &self.wells);
+        writer.put_list("beds", &self.beds);
+    }
+
+    fn load(reader: &mut SaveReader) -> Result<World, SaveError> {
+        Ok(World {
+            geography: Geography::load(reader)?,
+            items: ItemCatalogue::load(reader)?,
+            time: Time::load(reader)?,
+            weather: Weather::load(reader)?,
+            inventories: Arena::load("inventories", reader)?,
+            containers: Arena::load("containers", reader)?,
+            crops: Arena::load("crops", reader)?,
+            humans: Arena::load("humans", reader)?,
+            households: Arena::load("households", reader)?,
+            events: reader.get_list("events")?,
+            shrines: reader.get_list("shrines")?,
+            markets: reader.get_list("markets")?,
+            workshops: reader.get_list("workshops")?,
+            wells: reader.get_list("wells")?,
+            beds: reader.get_list("beds")?,
+        })
+    }
+}